@@ -0,0 +1,249 @@
+#![allow(dead_code)]
+use crate::core::engine::Event;
+use crate::core::log::MatchLog;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+/// The length of a candle. Candles are aligned to the Unix epoch, so daily candles start at
+/// midnight UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Interval {
+    OneSecond,
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+impl Interval {
+    pub const ALL: [Interval; 5] = [
+        Interval::OneSecond,
+        Interval::OneMinute,
+        Interval::FiveMinutes,
+        Interval::OneHour,
+        Interval::OneDay,
+    ];
+
+    /// Returns the length of the interval.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(match self {
+            Interval::OneSecond => 1,
+            Interval::OneMinute => 60,
+            Interval::FiveMinutes => 5 * 60,
+            Interval::OneHour => 60 * 60,
+            Interval::OneDay => 24 * 60 * 60,
+        })
+    }
+
+    /// Returns the start of the interval holding `time`.
+    pub fn start_of(&self, time: SystemTime) -> SystemTime {
+        let seconds = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+        let length = self.duration().as_secs();
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds - seconds % length)
+    }
+}
+
+/// The open, high, low and close prices and the traded volume of one instrument over one
+/// interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub symbol: String,     // The instrument traded.
+    pub interval: Interval, // The length of the candle.
+    pub start: SystemTime,  // The start of the interval, inclusive.
+    pub open: Decimal,      // The price of the first trade.
+    pub high: Decimal,      // The highest trade price.
+    pub low: Decimal,       // The lowest trade price.
+    pub close: Decimal,     // The price of the last trade.
+    pub volume: Decimal,    // The total traded size.
+    pub trade_count: usize, // The number of trades; zero for an interval without trades.
+}
+
+impl Candle {
+    /// Returns the end of the interval, exclusive.
+    pub fn end(&self) -> SystemTime {
+        self.start + self.interval.duration()
+    }
+
+    // A candle for an interval without trades, flat at the previous close
+    fn flat(symbol: &str, interval: Interval, start: SystemTime, price: Decimal) -> Self {
+        Candle {
+            symbol: symbol.to_string(),
+            interval,
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: dec!(0),
+            trade_count: 0,
+        }
+    }
+
+    fn add_trade(&mut self, price: Decimal, size: Decimal) {
+        if self.trade_count == 0 {
+            self.open = price;
+            self.high = price;
+            self.low = price;
+        }
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += size;
+        self.trade_count += 1;
+    }
+}
+
+// The candles of one instrument at one interval
+#[derive(Debug)]
+struct Series {
+    start: SystemTime,           // The start of the interval in progress.
+    current: Candle,             // The candle in progress; flat at the last close until a trade arrives.
+    finalized: VecDeque<Candle>, // The most recent finalized candles, oldest first.
+}
+
+/// Builds OHLCV candles from trades, per instrument and per interval.
+///
+/// A candle is finalized once a trade or a `tick` shows that its interval has ended, so a quiet
+/// market still gets its bars closed on time. Intervals without trades produce flat candles at
+/// the previous close with no volume; nothing is produced for an instrument before its first
+/// trade.
+///
+/// Trades are expected in time order. A trade timed before the interval in progress, e.g. one
+/// arriving after a tick closed its interval, is counted in the candle in progress.
+#[derive(Debug)]
+pub struct CandleAggregator {
+    intervals: Vec<Interval>,                     // The intervals candles are built at.
+    history_limit: usize,                         // The finalized candles kept per series.
+    series: BTreeMap<(String, Interval), Series>, // The candles, by instrument and interval.
+}
+
+impl CandleAggregator {
+    /// Creates an aggregator.
+    ///
+    /// # Arguments
+    /// * `intervals` - The intervals to build candles at, e.g. `&Interval::ALL`.
+    /// * `history_limit` - The number of finalized candles kept per instrument and interval.
+    pub fn new(intervals: &[Interval], history_limit: usize) -> Self {
+        Self {
+            intervals: intervals.to_vec(),
+            history_limit,
+            series: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the trades among the events of an engine.
+    ///
+    /// Each trade is timed by its `MatchLog`, which records the wall-clock time of the match, so
+    /// `tick` must be driven by the same wall clock. Callers with another notion of time should
+    /// pass the trades to `on_trade` themselves.
+    ///
+    /// # Arguments
+    /// * `symbol` - The instrument the engine trades.
+    /// * `events` - The events returned by `Engine::process`, in order.
+    ///
+    /// # Returns
+    /// * The candles finalized by the trades, oldest first.
+    pub fn on_events(&mut self, symbol: &str, events: &[Event]) -> Vec<Candle> {
+        let mut finalized = vec![];
+        for event in events {
+            if let Event::Log(log) = event {
+                if let Some(trade) = log.as_any().downcast_ref::<MatchLog>() {
+                    finalized.extend(self.on_trade(symbol, trade.price, trade.size, trade.time()));
+                }
+            }
+        }
+        finalized
+    }
+
+    /// Adds a trade.
+    ///
+    /// # Arguments
+    /// * `symbol` - The instrument traded.
+    /// * `price` - The trade price.
+    /// * `size` - The executed quantity.
+    /// * `time` - When the trade was executed.
+    ///
+    /// # Returns
+    /// * The candles of the instrument finalized because their interval ended before the trade,
+    ///   oldest first.
+    pub fn on_trade(&mut self, symbol: &str, price: Decimal, size: Decimal, time: SystemTime) -> Vec<Candle> {
+        let mut finalized = vec![];
+        for &interval in &self.intervals {
+            let history_limit = self.history_limit;
+            let series = self.series.entry((symbol.to_string(), interval)).or_insert_with(|| {
+                let start = interval.start_of(time);
+                Series {
+                    start,
+                    current: Candle::flat(symbol, interval, start, price),
+                    finalized: VecDeque::new(),
+                }
+            });
+            finalized.extend(advance(series, time, history_limit));
+            series.current.add_trade(price, size);
+        }
+        finalized.sort_by_key(|candle| candle.start);
+        finalized
+    }
+
+    /// Closes the candles of every instrument whose interval has ended by `now`.
+    ///
+    /// `now` must come from the clock the trades were timed with; see `on_events`.
+    ///
+    /// # Returns
+    /// * The finalized candles, by instrument, then oldest first.
+    pub fn tick(&mut self, now: SystemTime) -> Vec<Candle> {
+        let mut finalized = vec![];
+        for series in self.series.values_mut() {
+            finalized.extend(advance(series, now, self.history_limit));
+        }
+        finalized.sort_by(|a, b| (&a.symbol, a.start).cmp(&(&b.symbol, b.start)));
+        finalized
+    }
+
+    /// Returns the candle in progress for an instrument, if it has traded.
+    pub fn current(&self, symbol: &str, interval: Interval) -> Option<&Candle> {
+        self.series
+            .get(&(symbol.to_string(), interval))
+            .map(|series| &series.current)
+    }
+
+    /// Returns the most recent finalized candles of an instrument, oldest first.
+    pub fn finalized(&self, symbol: &str, interval: Interval) -> impl Iterator<Item = &Candle> {
+        self.series
+            .get(&(symbol.to_string(), interval))
+            .into_iter()
+            .flat_map(|series| series.finalized.iter())
+    }
+}
+
+/// Finalizes every candle of `series` whose interval ended by `now`, filling intervals without
+/// trades with flat candles.
+///
+/// At most `history_limit` flat candles are produced, the most recent ones, so a long quiet spell
+/// costs no more than the history kept; the series then continues at the interval holding `now`.
+fn advance(series: &mut Series, now: SystemTime, history_limit: usize) -> Vec<Candle> {
+    let length = series.current.interval.duration();
+    if series.start + length > now {
+        return vec![];
+    }
+    let (symbol, interval, close) = (series.current.symbol.clone(), series.current.interval, series.current.close);
+    let start = interval.start_of(now);
+    let empty = start.duration_since(series.start).unwrap_or_default().as_secs() / length.as_secs() - 1;
+    let filled = (empty as usize).min(history_limit);
+
+    let next = Candle::flat(&symbol, interval, start, close);
+    let mut finalized = vec![std::mem::replace(&mut series.current, next)];
+    for back in (1..=filled as u32).rev() {
+        finalized.push(Candle::flat(&symbol, interval, start - length * back, close));
+    }
+    series.start = start;
+    for candle in &finalized {
+        series.finalized.push_back(candle.clone());
+        if series.finalized.len() > history_limit {
+            series.finalized.pop_front();
+        }
+    }
+    finalized
+}
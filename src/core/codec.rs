@@ -0,0 +1,189 @@
+#![allow(dead_code)]
+use crate::core::error::StorageError;
+use rust_decimal::Decimal;
+use std::time::{Duration, SystemTime};
+
+/// Appends values to a byte buffer in the engine's binary format.
+///
+/// Integers are little-endian, strings and byte arrays are prefixed with their length as a `u32`,
+/// optional values are prefixed with a `0`/`1` presence byte, and decimals are stored in their
+/// 16-byte `Decimal::serialize` form.
+#[derive(Debug, Default)]
+pub(crate) struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the encoded bytes.
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn i64(&mut self, value: i64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub(crate) fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.buffer.extend_from_slice(value);
+    }
+
+    pub(crate) fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    pub(crate) fn decimal(&mut self, value: Decimal) {
+        self.buffer.extend_from_slice(&value.serialize());
+    }
+
+    /// Writes a time as seconds and nanoseconds since the Unix epoch. Times before the epoch are
+    /// written as the epoch itself.
+    pub(crate) fn time(&mut self, value: SystemTime) {
+        let since_epoch = value.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        self.u64(since_epoch.as_secs());
+        self.u32(since_epoch.subsec_nanos());
+    }
+
+    pub(crate) fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+}
+
+/// Reads values written by a `Writer` back from a byte slice.
+///
+/// Every read fails with `StorageError::Corrupt` instead of panicking when the data ends early or
+/// holds a value that cannot be decoded.
+#[derive(Debug)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    /// Returns the number of bytes not read yet.
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    pub(crate) fn take(&mut self, length: usize) -> Result<&'a [u8], StorageError> {
+        if self.remaining() < length {
+            return Err(StorageError::Corrupt(format!(
+                "unexpected end of data at byte {} (wanted {} more)",
+                self.offset, length
+            )));
+        }
+        let slice = &self.data[self.offset..self.offset + length];
+        self.offset += length;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StorageError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, StorageError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, StorageError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, StorageError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, StorageError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn i64(&mut self) -> Result<i64, StorageError> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, StorageError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(StorageError::Corrupt(format!("invalid boolean {}", other))),
+        }
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], StorageError> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, StorageError> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| StorageError::Corrupt("string is not valid UTF-8".to_string()))
+    }
+
+    pub(crate) fn decimal(&mut self) -> Result<Decimal, StorageError> {
+        Ok(Decimal::deserialize(self.array()?))
+    }
+
+    pub(crate) fn time(&mut self) -> Result<SystemTime, StorageError> {
+        let seconds = self.u64()?;
+        let nanos = self.u32()?;
+        SystemTime::UNIX_EPOCH
+            .checked_add(Duration::new(seconds, nanos))
+            .ok_or_else(|| StorageError::Corrupt("time out of range".to_string()))
+    }
+
+    pub(crate) fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, StorageError>,
+    ) -> Result<Option<T>, StorageError> {
+        match self.bool()? {
+            true => read(self).map(Some),
+            false => Ok(None),
+        }
+    }
+}
+
+/// Computes the CRC-32 (IEEE 802.3, as used by zip and gzip) of `data`.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
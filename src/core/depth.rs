@@ -0,0 +1,17 @@
+#![allow(dead_code)]
+use rust_decimal::Decimal;
+
+/// The orders resting at one price, aggregated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceLevel {
+    pub price: Decimal,     // The price of the level.
+    pub size: Decimal,      // The visible size at the level; hidden iceberg reserves are left out.
+    pub order_count: usize, // The number of orders resting at the level.
+}
+
+/// The top price levels of both sides of a book, best price first.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Depth {
+    pub bids: Vec<PriceLevel>, // Bid levels, highest price first.
+    pub asks: Vec<PriceLevel>, // Ask levels, lowest price first.
+}
@@ -0,0 +1,247 @@
+#![allow(dead_code)]
+use crate::core::codec::{Reader, Writer};
+use crate::core::error::{MatchingError, StorageError};
+use crate::core::journal::Journal;
+use crate::core::log::Log;
+use crate::core::order::{BidOrAsk, Order};
+use crate::core::order_book::OrderBook;
+use crate::core::snapshot::SnapshotData;
+use rust_decimal::Decimal;
+use std::time::SystemTime;
+
+/// A request to the matching engine.
+///
+/// Commands are the engine's only input: every change to the book is the result of processing
+/// one, so a recorded command stream can be replayed into a fresh engine to rebuild the same book.
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Places an order at its own price, dispatching on its `OrderType`. A `ReceivedLog` is
+    /// produced first, even if the order is then rejected or refused.
+    NewOrder(Order),
+    /// Cancels a resting order or pending stop order.
+    Cancel { order_id: String },
+    /// Amends the price and total remaining size of a resting order.
+    Amend { order_id: String, price: Decimal, size: Decimal },
+    /// Cancels every order matching the filters; `None` matches any owner or side.
+    MassCancel { owner: Option<String>, bid_or_ask: Option<BidOrAsk> },
+    /// Expires every good-till-date order due at or before the given time.
+    Expire { now: SystemTime },
+    /// Stops accepting new orders and amendments.
+    Halt,
+    /// Accepts new orders and amendments again after a halt.
+    Resume,
+    /// Captures the resting orders of the book.
+    Snapshot,
+}
+
+impl Command {
+    /// Writes the command in the engine's binary format.
+    pub(crate) fn encode(&self, writer: &mut Writer) {
+        match self {
+            Command::NewOrder(order) => {
+                writer.u8(0);
+                order.encode(writer);
+            }
+            Command::Cancel { order_id } => {
+                writer.u8(1);
+                writer.string(order_id);
+            }
+            Command::Amend { order_id, price, size } => {
+                writer.u8(2);
+                writer.string(order_id);
+                writer.decimal(*price);
+                writer.decimal(*size);
+            }
+            Command::MassCancel { owner, bid_or_ask } => {
+                writer.u8(3);
+                writer.option(owner.as_deref(), Writer::string);
+                writer.option(bid_or_ask.as_ref(), |writer, side| side.encode(writer));
+            }
+            Command::Expire { now } => {
+                writer.u8(4);
+                writer.time(*now);
+            }
+            Command::Halt => writer.u8(5),
+            Command::Resume => writer.u8(6),
+            Command::Snapshot => writer.u8(7),
+        }
+    }
+
+    /// Reads a command written by `encode`.
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self, StorageError> {
+        Ok(match reader.u8()? {
+            0 => Command::NewOrder(Order::decode(reader)?),
+            1 => Command::Cancel { order_id: reader.string()? },
+            2 => Command::Amend {
+                order_id: reader.string()?,
+                price: reader.decimal()?,
+                size: reader.decimal()?,
+            },
+            3 => Command::MassCancel {
+                owner: reader.option(Reader::string)?,
+                bid_or_ask: reader.option(BidOrAsk::decode)?,
+            },
+            4 => Command::Expire { now: reader.time()? },
+            5 => Command::Halt,
+            6 => Command::Resume,
+            7 => Command::Snapshot,
+            tag => return Err(StorageError::Corrupt(format!("unknown command {}", tag))),
+        })
+    }
+}
+
+/// An outcome reported by the matching engine while processing a `Command`.
+#[derive(Debug)]
+pub enum Event {
+    /// A log produced by the book, in the order it was produced.
+    Log(Box<dyn Log>),
+    /// The command was refused and left the orders unchanged. A refused `NewOrder` still follows
+    /// its `ReceivedLog`, which advances the log sequence.
+    Error(MatchingError),
+    /// The book was halted.
+    Halted,
+    /// The book was resumed.
+    Resumed,
+    /// A snapshot of the book.
+    Snapshot(SnapshotData),
+}
+
+/// Drives an `OrderBook` from a stream of `Command`s.
+///
+/// `process` is the only way to change the book, and it depends on nothing but the current state
+/// and the command (times are carried by the commands themselves), so processing the same
+/// commands in the same order always yields the same events and the same book.
+///
+/// With a journal attached, every command is written to it before it is processed, followed by
+/// the logs it produced.
+#[derive(Debug)]
+pub struct Engine {
+    pair: String,             // The instrument traded on the book, e.g. "BTC/USD".
+    book: OrderBook,          // The book the commands are applied to.
+    journal: Option<Journal>, // The write-ahead journal, if the engine is durable.
+}
+
+impl Engine {
+    /// Creates an engine around a new, empty order book.
+    ///
+    /// # Arguments
+    /// * `pair` - The instrument traded on the book.
+    pub fn new(pair: String) -> Self {
+        Self::with_book(pair, OrderBook::new())
+    }
+
+    /// Creates an engine around an already configured order book.
+    ///
+    /// # Arguments
+    /// * `pair` - The instrument traded on the book.
+    /// * `book` - The book to drive, e.g. one built with `OrderBook::with_spec`.
+    pub fn with_book(pair: String, book: OrderBook) -> Self {
+        Self { pair, book, journal: None }
+    }
+
+    /// Creates an engine around a book restored from a snapshot.
+    ///
+    /// # Arguments
+    /// * `book` - An empty book, configured like the one the snapshot was taken from.
+    /// * `snapshot` - The snapshot to restore; the engine trades its pair.
+    ///
+    /// # Returns
+    /// * The engine, or the `MatchingError` that prevented the snapshot from being restored.
+    pub fn from_snapshot(mut book: OrderBook, snapshot: SnapshotData) -> Result<Self, MatchingError> {
+        let pair = snapshot.pair().to_string();
+        book.restore(snapshot)?;
+        Ok(Self::with_book(pair, book))
+    }
+
+    /// Attaches a write-ahead journal to the engine.
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Returns the instrument traded on the book.
+    pub fn pair(&self) -> &str {
+        &self.pair
+    }
+
+    /// Returns a read-only view of the book.
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// Applies a command to the book.
+    ///
+    /// # Arguments
+    /// * `command` - The `Command` to apply.
+    ///
+    /// # Returns
+    /// * The `Event`s produced by the command. A refused command yields a single `Event::Error`,
+    ///   except a refused `NewOrder`, which yields its `ReceivedLog` and then the `Event::Error`.
+    ///   A command that could not be journaled yields a single `Event::Error` and is not
+    ///   processed at all. If the resulting logs cannot be journaled, an `Event::Error` follows
+    ///   them.
+    pub fn process(&mut self, command: Command) -> Vec<Event> {
+        if let Some(journal) = &mut self.journal {
+            if let Err(error) = journal.append_command(self.book.log_seq(), &command) {
+                return vec![Event::Error(MatchingError::JournalUnavailable(error.to_string()))];
+            }
+        }
+
+        let mut events = self.apply(command);
+        if let Some(journal) = &mut self.journal {
+            let written: Result<(), StorageError> = events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Log(log) => Some(log.as_ref()),
+                    _ => None,
+                })
+                .try_for_each(|log| journal.append_log(log));
+            if let Err(error) = written {
+                events.push(Event::Error(MatchingError::JournalUnavailable(error.to_string())));
+            }
+        }
+        events
+    }
+
+    fn apply(&mut self, command: Command) -> Vec<Event> {
+        match command {
+            Command::NewOrder(order) => {
+                let received = Event::Log(Box::new(self.book.receive_order(&order)));
+                let mut events = Self::logs(self.book.place_order(order));
+                events.insert(0, received);
+                events
+            }
+            Command::Cancel { order_id } => Self::logs(
+                self.book
+                    .cancel_order(&order_id)
+                    .map(|log| vec![Box::new(log) as Box<dyn Log>]),
+            ),
+            Command::Amend { order_id, price, size } => {
+                Self::logs(self.book.amend_order(&order_id, price, size))
+            }
+            Command::MassCancel { owner, bid_or_ask } => Self::logs(Ok(self
+                .book
+                .mass_cancel(owner.as_deref(), bid_or_ask.as_ref()))),
+            Command::Expire { now } => Self::logs(Ok(self.book.expire_orders(now))),
+            Command::Halt => {
+                self.book.halt();
+                vec![Event::Halted]
+            }
+            Command::Resume => {
+                self.book.resume();
+                vec![Event::Resumed]
+            }
+            Command::Snapshot => match self.book.snapshot(self.pair.clone()) {
+                Ok(snapshot) => vec![Event::Snapshot(snapshot)],
+                Err(error) => vec![Event::Error(error)],
+            },
+        }
+    }
+
+    fn logs(result: Result<Vec<Box<dyn Log>>, MatchingError>) -> Vec<Event> {
+        match result {
+            Ok(logs) => logs.into_iter().map(Event::Log).collect(),
+            Err(error) => vec![Event::Error(error)],
+        }
+    }
+}
@@ -0,0 +1,146 @@
+#![allow(dead_code)]
+use crate::core::log::RejectReason;
+use rust_decimal::Decimal;
+use std::fmt;
+
+/// Errors returned by the matching engine when a request cannot be applied to the book.
+///
+/// Every public entry point of `OrderBook` reports bad requests through this type instead of
+/// panicking, so a single bad request never takes down the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchingError {
+    /// No resting order with the given ID exists in the book.
+    UnknownOrder(String),
+    /// An order with the given ID is already resting or pending in the book.
+    DuplicateOrderId(String),
+    /// The price is zero or negative.
+    InvalidPrice(Decimal),
+    /// The size is zero or negative.
+    InvalidSize(Decimal),
+    /// The order with the given ID was found on the wrong side of the book.
+    SideMismatch(String),
+    /// The book is halted and does not accept new orders or amendments.
+    BookHalted,
+    /// The order does not conform to the instrument specification of the book.
+    Rejected(RejectReason),
+    /// The command could not be written to the journal, so it was not processed.
+    JournalUnavailable(String),
+    /// No instrument with the given symbol is listed.
+    UnknownInstrument(String),
+    /// An instrument with the given symbol is already listed.
+    DuplicateInstrument(String),
+    /// The book of an engine being listed under the given symbol is not configured as the
+    /// instrument's configuration describes.
+    ConfigMismatch(String),
+}
+
+impl fmt::Display for MatchingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchingError::UnknownOrder(id) => write!(f, "unknown order: {}", id),
+            MatchingError::DuplicateOrderId(id) => write!(f, "duplicate order id: {}", id),
+            MatchingError::InvalidPrice(price) => write!(f, "invalid price: {}", price),
+            MatchingError::InvalidSize(size) => write!(f, "invalid size: {}", size),
+            MatchingError::SideMismatch(id) => write!(f, "order on the wrong side of the book: {}", id),
+            MatchingError::BookHalted => write!(f, "book is halted"),
+            MatchingError::Rejected(reason) => write!(f, "order rejected: {}", reason.code()),
+            MatchingError::JournalUnavailable(reason) => write!(f, "journal unavailable: {}", reason),
+            MatchingError::UnknownInstrument(symbol) => write!(f, "unknown instrument: {}", symbol),
+            MatchingError::DuplicateInstrument(symbol) => write!(f, "instrument already listed: {}", symbol),
+            MatchingError::ConfigMismatch(symbol) => write!(f, "book does not match the instrument configuration: {}", symbol),
+        }
+    }
+}
+
+impl std::error::Error for MatchingError {}
+
+/// Errors returned when reading or writing the engine's files.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The file could not be read or written.
+    Io(std::io::Error),
+    /// The file does not start with the expected magic bytes, so it is not a file of this kind.
+    BadMagic,
+    /// The file was written in a format version this build cannot read.
+    UnsupportedVersion(u16),
+    /// The contents do not match the checksum stored with them.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The contents are structurally invalid, e.g. truncated or holding an unknown tag.
+    Corrupt(String),
+    /// Replaying the journal produced a log sequence other than the one recorded in it.
+    SequenceMismatch { expected: i64, actual: i64 },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(error) => write!(f, "i/o error: {}", error),
+            StorageError::BadMagic => write!(f, "not a file of the expected kind (bad magic)"),
+            StorageError::UnsupportedVersion(version) => write!(f, "unsupported format version: {}", version),
+            StorageError::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch: expected {:08x}, found {:08x}", expected, actual)
+            }
+            StorageError::Corrupt(reason) => write!(f, "corrupt data: {}", reason),
+            StorageError::SequenceMismatch { expected, actual } => {
+                write!(f, "sequence mismatch: expected {}, found {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(error: std::io::Error) -> Self {
+        StorageError::Io(error)
+    }
+}
+
+/// Errors raised by consumers of the market-data feeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedError {
+    /// An update arrived out of order; updates between the expected and the received sequence
+    /// were missed and the consumer must bootstrap again from a fresh snapshot.
+    SequenceGap { expected: i64, actual: i64 },
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::SequenceGap { expected, actual } => {
+                write!(f, "feed sequence gap: expected update {}, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+/// Checks the sequence of a feed update against the last one a consumer applied.
+///
+/// # Arguments
+/// * `last` - The sequence of the last update applied.
+/// * `received` - The sequence of the update that arrived.
+///
+/// # Returns
+/// * `Ok(true)` if the update is the next one and must be applied, `Ok(false)` if the consumer
+///   already covers it, or `FeedError::SequenceGap` if updates in between were missed.
+pub(crate) fn check_sequence(last: i64, received: i64) -> Result<bool, FeedError> {
+    if received <= last {
+        return Ok(false);
+    }
+    if received != last + 1 {
+        return Err(FeedError::SequenceGap {
+            expected: last + 1,
+            actual: received,
+        });
+    }
+    Ok(true)
+}
@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+use crate::core::engine::{Command, Engine, Event};
+use crate::core::error::MatchingError;
+use crate::core::instrument::InstrumentConfig;
+use crate::core::order_book::OrderBook;
+use crate::core::snapshot::SnapshotData;
+use std::collections::BTreeMap;
+
+// An instrument listed on the exchange
+#[derive(Debug)]
+struct Listing {
+    config: InstrumentConfig, // The configuration the instrument was listed with.
+    engine: Engine,           // The engine driving the instrument's book.
+}
+
+/// A registry of instruments, each traded on its own `OrderBook` behind its own `Engine`.
+///
+/// Commands are routed to the engine of the symbol they name. Instruments are kept in symbol
+/// order, so iterating over them and snapshotting them is deterministic.
+#[derive(Debug, Default)]
+pub struct Exchange {
+    listings: BTreeMap<String, Listing>, // The listed instruments, by symbol.
+}
+
+impl Exchange {
+    /// Creates an exchange with no instruments listed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lists a new instrument with an empty book.
+    ///
+    /// # Arguments
+    /// * `symbol` - The symbol commands for the instrument are routed by, e.g. "BTC/USD".
+    /// * `config` - The trading rules and self-trade prevention mode of the instrument.
+    ///
+    /// # Returns
+    /// * `Ok(())`, or `MatchingError::DuplicateInstrument` if the symbol is already listed.
+    pub fn list(&mut self, symbol: &str, config: InstrumentConfig) -> Result<(), MatchingError> {
+        let engine = Engine::with_book(symbol.to_string(), Self::new_book(&config));
+        self.insert(symbol, config, engine)
+    }
+
+    /// Lists an instrument with its book restored from a snapshot.
+    ///
+    /// # Arguments
+    /// * `config` - The trading rules and self-trade prevention mode of the instrument.
+    /// * `snapshot` - The snapshot to restore; the instrument is listed under its pair.
+    ///
+    /// # Returns
+    /// * `Ok(())`, `MatchingError::DuplicateInstrument` if the pair is already listed, or the
+    ///   `MatchingError` that prevented the snapshot from being restored.
+    pub fn list_from_snapshot(&mut self, config: InstrumentConfig, snapshot: SnapshotData) -> Result<(), MatchingError> {
+        let symbol = snapshot.pair().to_string();
+        if self.listings.contains_key(&symbol) {
+            return Err(MatchingError::DuplicateInstrument(symbol));
+        }
+        let engine = Engine::from_snapshot(Self::new_book(&config), snapshot)?;
+        self.insert(&symbol, config, engine)
+    }
+
+    /// Lists an instrument driven by an already built engine, e.g. one with a journal attached
+    /// or one returned by recovery. The engine's pair is used as the symbol.
+    ///
+    /// The book may already hold orders, so `config` is not applied to it; the book must have
+    /// been built with the same specification and self-trade prevention mode instead.
+    ///
+    /// # Returns
+    /// * `Ok(())`, `MatchingError::DuplicateInstrument` if the pair is already listed, or
+    ///   `MatchingError::ConfigMismatch` if the book is configured differently from `config`.
+    pub fn list_engine(&mut self, config: InstrumentConfig, engine: Engine) -> Result<(), MatchingError> {
+        let symbol = engine.pair().to_string();
+        let book = engine.book();
+        if *book.spec() != config.spec || book.self_trade_prevention() != config.self_trade_prevention.as_ref() {
+            return Err(MatchingError::ConfigMismatch(symbol));
+        }
+        self.insert(&symbol, config, engine)
+    }
+
+    /// Removes an instrument from the exchange.
+    ///
+    /// Orders resting in the book are left untouched; the returned engine still holds them, so
+    /// the caller can snapshot or cancel them.
+    ///
+    /// # Returns
+    /// * The engine of the instrument, or `MatchingError::UnknownInstrument` if the symbol is not
+    ///   listed.
+    pub fn delist(&mut self, symbol: &str) -> Result<Engine, MatchingError> {
+        self.listings
+            .remove(symbol)
+            .map(|listing| listing.engine)
+            .ok_or_else(|| MatchingError::UnknownInstrument(symbol.to_string()))
+    }
+
+    /// Routes a command to the engine of an instrument.
+    ///
+    /// # Arguments
+    /// * `symbol` - The instrument the command is for.
+    /// * `command` - The `Command` to apply.
+    ///
+    /// # Returns
+    /// * The `Event`s produced by the engine, or a single `Event::Error` with
+    ///   `MatchingError::UnknownInstrument` if the symbol is not listed.
+    pub fn process(&mut self, symbol: &str, command: Command) -> Vec<Event> {
+        match self.listings.get_mut(symbol) {
+            Some(listing) => listing.engine.process(command),
+            None => vec![Event::Error(MatchingError::UnknownInstrument(symbol.to_string()))],
+        }
+    }
+
+    /// Returns the symbols of the listed instruments, in order.
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.listings.keys().map(String::as_str)
+    }
+
+    /// Returns the engine of an instrument, if it is listed.
+    pub fn engine(&self, symbol: &str) -> Option<&Engine> {
+        self.listings.get(symbol).map(|listing| &listing.engine)
+    }
+
+    /// Returns the configuration of an instrument, if it is listed.
+    pub fn config(&self, symbol: &str) -> Option<&InstrumentConfig> {
+        self.listings.get(symbol).map(|listing| &listing.config)
+    }
+
+    /// Takes a snapshot of every listed book.
+    ///
+    /// The exchange applies one command at a time, so all the snapshots describe the books at
+    /// the same point: after the last command processed and before the next one.
+    ///
+    /// # Returns
+    /// * The snapshots in symbol order, or the first `MatchingError` raised while taking them.
+    pub fn snapshot_all(&self) -> Result<Vec<SnapshotData>, MatchingError> {
+        self.listings
+            .iter()
+            .map(|(symbol, listing)| listing.engine.book().snapshot(symbol.clone()))
+            .collect()
+    }
+
+    fn insert(&mut self, symbol: &str, config: InstrumentConfig, engine: Engine) -> Result<(), MatchingError> {
+        if self.listings.contains_key(symbol) {
+            return Err(MatchingError::DuplicateInstrument(symbol.to_string()));
+        }
+        self.listings.insert(symbol.to_string(), Listing { config, engine });
+        Ok(())
+    }
+
+    fn new_book(config: &InstrumentConfig) -> OrderBook {
+        let mut book = OrderBook::with_spec(config.spec.clone());
+        book.set_self_trade_prevention(config.self_trade_prevention.clone());
+        book
+    }
+}
@@ -0,0 +1,106 @@
+#![allow(dead_code)]
+use crate::core::log::RejectReason;
+use crate::core::order::{Order, SelfTradePrevention};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+/// The trading rules of the instrument an `OrderBook` serves.
+///
+/// Every order entering the book is checked against the specification, and orders that do not
+/// conform are rejected with a `RejectLog` carrying the matching `RejectReason`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentSpec {
+    pub tick_size: Decimal,            // The minimum price increment.
+    pub lot_size: Option<Decimal>,     // Sizes must be a multiple of this, if set.
+    pub min_size: Option<Decimal>,     // The smallest accepted order size, if set.
+    pub max_size: Option<Decimal>,     // The largest accepted order size, if set.
+    pub min_notional: Option<Decimal>, // The smallest accepted price times size, if set.
+    pub price_precision: Option<u32>,  // The maximum number of decimal places in a price, if set.
+}
+
+impl Default for InstrumentSpec {
+    fn default() -> Self {
+        InstrumentSpec {
+            tick_size: dec!(0.01),
+            lot_size: None,
+            min_size: None,
+            max_size: None,
+            min_notional: None,
+            price_precision: None,
+        }
+    }
+}
+
+/// The configuration an instrument is listed with on an `Exchange`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InstrumentConfig {
+    pub spec: InstrumentSpec,                               // The trading rules of the instrument.
+    pub self_trade_prevention: Option<SelfTradePrevention>, // How self trades are resolved, if prevented.
+}
+
+impl InstrumentSpec {
+    /// Checks an incoming order against the specification.
+    ///
+    /// # Arguments
+    /// * `order` - The incoming order.
+    /// * `limit_price` - The price the order rests or trades at, or `None` for a market order.
+    ///
+    /// # Returns
+    /// * `Ok(())`, or the `RejectReason` for the first rule the order breaks.
+    pub fn validate(&self, order: &Order, limit_price: Option<Decimal>) -> Result<(), RejectReason> {
+        if let Some(price) = limit_price {
+            self.validate_price(price)?;
+        }
+        if let Some(stop_price) = order.stop_price() {
+            self.validate_price(stop_price)?;
+        }
+        self.validate_size(order.total_size())?;
+        if let Some(display_size) = order.display_size {
+            if display_size <= dec!(0) || !is_multiple(display_size, self.lot_size) {
+                return Err(RejectReason::InvalidDisplaySize);
+            }
+        }
+        if let (Some(price), Some(min_notional)) = (limit_price, self.min_notional) {
+            if price * order.total_size() < min_notional {
+                return Err(RejectReason::NotionalBelowMinimum);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a price against the tick size and price precision.
+    pub fn validate_price(&self, price: Decimal) -> Result<(), RejectReason> {
+        if let Some(precision) = self.price_precision {
+            if price.normalize().scale() > precision {
+                return Err(RejectReason::PricePrecisionExceeded);
+            }
+        }
+        if !is_multiple(price, Some(self.tick_size)) {
+            return Err(RejectReason::PriceNotOnTick);
+        }
+        Ok(())
+    }
+
+    /// Checks a size against the lot size and the size bounds.
+    pub fn validate_size(&self, size: Decimal) -> Result<(), RejectReason> {
+        if !is_multiple(size, self.lot_size) {
+            return Err(RejectReason::SizeNotOnLot);
+        }
+        if self.min_size.is_some_and(|min_size| size < min_size) {
+            return Err(RejectReason::SizeBelowMinimum);
+        }
+        if self.max_size.is_some_and(|max_size| size > max_size) {
+            return Err(RejectReason::SizeAboveMaximum);
+        }
+        Ok(())
+    }
+}
+
+/// Checks whether `value` is a whole multiple of `step`. A missing or non-positive step accepts
+/// any value.
+fn is_multiple(value: Decimal, step: Option<Decimal>) -> bool {
+    match step {
+        Some(step) if step > dec!(0) => (value % step).is_zero(),
+        _ => true,
+    }
+}
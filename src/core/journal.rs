@@ -0,0 +1,304 @@
+#![allow(dead_code)]
+use crate::core::codec::{crc32, Reader, Writer};
+use crate::core::engine::Command;
+use crate::core::error::StorageError;
+use crate::core::log::{decode_log, Log};
+use crate::core::snapshot_store::sync_directory;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// The bytes every journal segment starts with.
+const JOURNAL_MAGIC: [u8; 4] = *b"MEJL";
+/// The journal format version written by this build.
+const JOURNAL_VERSION: u16 = 1;
+/// The length of the segment header: the magic bytes and the format version.
+const HEADER_LENGTH: u64 = 6;
+/// The length of the frame in front of every record: its length and its CRC-32.
+const FRAME_LENGTH: usize = 8;
+const SEGMENT_EXTENSION: &str = "journal";
+
+/// When the journal forces appended records to disk.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncPolicy {
+    /// Sync after every record. Nothing acknowledged is ever lost.
+    EveryRecord,
+    /// Sync once the given number of records has been appended since the last sync.
+    Batched(usize),
+    /// Sync on the first append after the given time has passed since the last sync.
+    Interval(Duration),
+}
+
+/// How a `Journal` writes its segments.
+#[derive(Debug, Clone)]
+pub struct JournalConfig {
+    pub sync_policy: SyncPolicy, // When appended records are forced to disk.
+    pub max_segment_bytes: u64,  // The size after which a new segment is started.
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            sync_policy: SyncPolicy::EveryRecord,
+            max_segment_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// A record read back from the journal.
+#[derive(Debug)]
+pub enum JournalEntry {
+    /// A command, written before the engine processed it. `log_seq` is the sequence of the last
+    /// log the book had produced at that point.
+    Command { log_seq: i64, command: Command },
+    /// A log produced by the engine while processing the preceding command.
+    Log(Box<dyn Log>),
+}
+
+impl JournalEntry {
+    fn decode(reader: &mut Reader) -> Result<Self, StorageError> {
+        match reader.u8()? {
+            0 => Ok(JournalEntry::Command {
+                log_seq: reader.i64()?,
+                command: Command::decode(reader)?,
+            }),
+            1 => Ok(JournalEntry::Log(decode_log(reader)?)),
+            tag => Err(StorageError::Corrupt(format!("unknown journal entry {}", tag))),
+        }
+    }
+}
+
+/// Everything read back from a journal directory.
+#[derive(Debug)]
+pub struct JournalContents {
+    pub entries: Vec<JournalEntry>,  // The complete records, oldest first.
+    pub torn_tail: Option<TornTail>, // The partially written record ending the journal, if any.
+}
+
+/// A partially written record at the end of the last segment, left behind by a crash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TornTail {
+    pub path: PathBuf, // The segment holding the torn record.
+    pub offset: u64,   // The length of the segment up to the end of its last complete record.
+}
+
+/// An append-only, segmented write-ahead journal of engine commands and logs.
+///
+/// Segments are files named `<index>.journal` with a zero-padded index, each starting with the
+/// magic bytes `MEJL` and the format version. Every record in a segment is framed by its length
+/// (`u32`) and the CRC-32 (`u32`) of its payload, so a torn or corrupted record is detected when
+/// the journal is read back. A new segment is started when the current one would grow beyond
+/// `JournalConfig::max_segment_bytes`.
+#[derive(Debug)]
+pub struct Journal {
+    directory: PathBuf,      // The directory holding the segments.
+    config: JournalConfig,   // The sync policy and segment size.
+    writer: BufWriter<File>, // The segment being appended to.
+    segment: u64,            // The index of the segment being appended to.
+    segment_bytes: u64,      // The bytes written to the current segment, including its header.
+    unsynced: usize,         // The records appended since the last sync.
+    last_sync: Instant,      // When the journal was last synced.
+}
+
+impl Journal {
+    /// Opens the journal in `directory`, creating the directory if it does not exist.
+    ///
+    /// Appending always starts in a new segment after any existing ones. Existing segments are
+    /// never modified, so a torn tail left by a crash must be truncated (by recovery) before the
+    /// journal is opened again.
+    pub fn open(directory: impl Into<PathBuf>, config: JournalConfig) -> Result<Self, StorageError> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        let segment = segments(&directory)?.last().map_or(0, |(index, _)| index + 1);
+        let writer = create_segment(&directory, segment)?;
+
+        Ok(Self {
+            directory,
+            config,
+            writer,
+            segment,
+            segment_bytes: HEADER_LENGTH,
+            unsynced: 0,
+            last_sync: Instant::now(),
+        })
+    }
+
+    /// Appends a command before it is processed.
+    ///
+    /// # Arguments
+    /// * `log_seq` - The sequence of the last log the book produced before the command.
+    /// * `command` - The command about to be processed.
+    pub fn append_command(&mut self, log_seq: i64, command: &Command) -> Result<(), StorageError> {
+        let mut writer = Writer::new();
+        writer.u8(0);
+        writer.i64(log_seq);
+        command.encode(&mut writer);
+        self.append(writer.into_bytes())
+    }
+
+    /// Appends a log produced by the engine.
+    pub fn append_log(&mut self, log: &dyn Log) -> Result<(), StorageError> {
+        let mut writer = Writer::new();
+        writer.u8(1);
+        log.encode(&mut writer);
+        self.append(writer.into_bytes())
+    }
+
+    fn append(&mut self, payload: Vec<u8>) -> Result<(), StorageError> {
+        let record_length = (FRAME_LENGTH + payload.len()) as u64;
+        if self.segment_bytes > HEADER_LENGTH && self.segment_bytes + record_length > self.config.max_segment_bytes {
+            self.rotate()?;
+        }
+
+        let mut frame = Writer::new();
+        frame.u32(payload.len() as u32);
+        frame.u32(crc32(&payload));
+        self.writer.write_all(&frame.into_bytes())?;
+        self.writer.write_all(&payload)?;
+        self.segment_bytes += record_length;
+        self.unsynced += 1;
+
+        let due = match &self.config.sync_policy {
+            SyncPolicy::EveryRecord => true,
+            SyncPolicy::Batched(records) => self.unsynced >= *records,
+            SyncPolicy::Interval(interval) => self.last_sync.elapsed() >= *interval,
+        };
+        if due {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Forces every appended record to disk.
+    pub fn sync(&mut self) -> Result<(), StorageError> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Returns the number of records appended since the last sync.
+    pub fn unsynced(&self) -> usize {
+        self.unsynced
+    }
+
+    fn rotate(&mut self) -> Result<(), StorageError> {
+        self.sync()?;
+        self.segment += 1;
+        self.writer = create_segment(&self.directory, self.segment)?;
+        self.segment_bytes = HEADER_LENGTH;
+        Ok(())
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        let _ = self.sync(); // Best effort; callers wanting guarantees sync explicitly.
+    }
+}
+
+/// Reads every record of the journal in `directory`, oldest first.
+///
+/// A record cut short at the very end of the last segment is the expected result of a crash
+/// during an append; it is left out and reported as the `torn_tail`. A damaged record anywhere
+/// else means the journal itself is corrupt.
+///
+/// # Returns
+/// * The records and any torn tail, or the `StorageError` describing the damage.
+pub fn read_journal(directory: &Path) -> Result<JournalContents, StorageError> {
+    let segments = segments(directory)?;
+    let mut entries = vec![];
+    let mut torn_tail = None;
+
+    for (number, (_, path)) in segments.iter().enumerate() {
+        let last = number + 1 == segments.len();
+        let bytes = fs::read(path)?;
+        if let Some(offset) = read_segment(&bytes, last, &mut entries)? {
+            if !last {
+                return Err(StorageError::Corrupt(format!(
+                    "{} ends in a torn record but is not the last segment",
+                    path.display()
+                )));
+            }
+            torn_tail = Some(TornTail { path: path.clone(), offset });
+        }
+    }
+
+    Ok(JournalContents { entries, torn_tail })
+}
+
+/// Reads the records of one segment into `entries`.
+///
+/// # Returns
+/// * `Some(offset)` with the length of the intact part if the segment ends in a torn record, or
+///   `None` if it ends cleanly.
+fn read_segment(bytes: &[u8], last: bool, entries: &mut Vec<JournalEntry>) -> Result<Option<u64>, StorageError> {
+    if bytes.is_empty() {
+        return Ok(None); // Created but never written, or truncated back to nothing.
+    }
+    let mut reader = Reader::new(bytes);
+    if bytes.len() < HEADER_LENGTH as usize {
+        return Ok(Some(0)); // The header itself was torn.
+    }
+    if reader.take(JOURNAL_MAGIC.len())? != JOURNAL_MAGIC {
+        return Err(StorageError::BadMagic);
+    }
+    let version = reader.u16()?;
+    if version != JOURNAL_VERSION {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+
+    while reader.remaining() > 0 {
+        let offset = (bytes.len() - reader.remaining()) as u64;
+        if reader.remaining() < FRAME_LENGTH {
+            return Ok(Some(offset));
+        }
+        let length = reader.u32()? as usize;
+        let expected = reader.u32()?;
+        if reader.remaining() < length {
+            return Ok(Some(offset));
+        }
+        let payload = reader.take(length)?;
+        let actual = crc32(payload);
+        if actual != expected {
+            if last && reader.remaining() == 0 {
+                return Ok(Some(offset)); // The final write did not reach the disk in full.
+            }
+            return Err(StorageError::ChecksumMismatch { expected, actual });
+        }
+        entries.push(JournalEntry::decode(&mut Reader::new(payload))?);
+    }
+    Ok(None)
+}
+
+/// Lists the segments in `directory`, lowest index first.
+pub(crate) fn segments(directory: &Path) -> Result<Vec<(u64, PathBuf)>, StorageError> {
+    let mut segments = vec![];
+    if !directory.exists() {
+        return Ok(segments);
+    }
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        if let Some(index) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse().ok()) {
+            segments.push((index, path));
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+fn create_segment(directory: &Path, index: u64) -> Result<BufWriter<File>, StorageError> {
+    let path = directory.join(format!("{:010}.{}", index, SEGMENT_EXTENSION));
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    let mut header = JOURNAL_MAGIC.to_vec();
+    header.extend_from_slice(&JOURNAL_VERSION.to_le_bytes());
+    file.write_all(&header)?;
+    file.sync_all()?;
+    sync_directory(directory)?;
+    Ok(BufWriter::new(file))
+}
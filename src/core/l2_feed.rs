@@ -0,0 +1,253 @@
+#![allow(dead_code)]
+use crate::core::depth::{Depth, PriceLevel};
+use crate::core::engine::Event;
+use crate::core::error::{check_sequence, FeedError};
+use crate::core::log::{AmendLog, DoneLog, Log, MatchLog, OpenLog};
+use crate::core::order::BidOrAsk;
+use crate::core::order_book::OrderBook;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::{BTreeMap, HashMap};
+
+/// The new state of one price level, published whenever the level changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelUpdate {
+    pub sequence: i64,        // The position of the update in the feed, without gaps.
+    pub bid_or_ask: BidOrAsk, // The side of the level.
+    pub price: Decimal,       // The price of the level.
+    pub size: Decimal,        // The new visible size at the level; zero once the level is gone.
+    pub order_count: usize,   // The new number of orders resting at the level.
+}
+
+/// The full level-2 state of the feed, used to bootstrap a client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct L2Snapshot {
+    pub sequence: i64, // The sequence of the last update reflected in the snapshot.
+    pub depth: Depth,  // Every level of both sides, best price first.
+}
+
+// A resting order as far as the feed knows it
+#[derive(Debug, Clone)]
+struct RestingOrder {
+    bid_or_ask: BidOrAsk,
+    price: Decimal,
+    size: Decimal, // The visible size.
+}
+
+/// Turns the logs of an engine into level-2 updates.
+///
+/// The publisher follows every resting order through the `OpenLog`, `MatchLog`, `DoneLog` and
+/// `AmendLog`s of the book and keeps the aggregated levels they add up to. After each batch of
+/// logs, one `LevelUpdate` is published for every level the batch changed, carrying the final
+/// state of the level.
+///
+/// A client bootstraps by buffering updates, fetching a `snapshot`, dropping the buffered updates
+/// it already covers and applying the rest to an `L2Book`; a gap in the update sequences means
+/// updates were lost and the client must bootstrap again.
+#[derive(Debug, Default)]
+pub struct L2Publisher {
+    orders: HashMap<String, RestingOrder>, // The resting orders, by ID.
+    bids: BTreeMap<Decimal, PriceLevel>,   // The aggregated bid levels.
+    asks: BTreeMap<Decimal, PriceLevel>,   // The aggregated ask levels.
+    sequence: i64,                         // The sequence of the last published update.
+}
+
+impl L2Publisher {
+    /// Creates a publisher for an empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a publisher for a book that already holds orders, e.g. one restored from a
+    /// snapshot. Logs produced by the book from now on are expected to be published.
+    pub fn from_book(book: &OrderBook) -> Self {
+        let mut publisher = Self::new();
+        for limit in book.ask_limits().chain(book.bid_limits()) {
+            for order in &limit.orders {
+                publisher.add(order.id.clone(), order.bid_or_ask.clone(), order.price, order.size);
+            }
+        }
+        publisher
+    }
+
+    /// Applies the logs among `events` and publishes the resulting level changes.
+    ///
+    /// # Arguments
+    /// * `events` - The events returned by `Engine::process`, in order.
+    ///
+    /// # Returns
+    /// * One `LevelUpdate` per changed level, in the order the levels were first changed.
+    pub fn publish(&mut self, events: &[Event]) -> Vec<LevelUpdate> {
+        let mut touched: Vec<(BidOrAsk, Decimal)> = vec![];
+        for event in events {
+            if let Event::Log(log) = event {
+                self.apply(log.as_ref(), &mut touched);
+            }
+        }
+
+        touched
+            .into_iter()
+            .map(|(bid_or_ask, price)| {
+                self.sequence += 1;
+                let level = self.levels(&bid_or_ask).get(&price);
+                LevelUpdate {
+                    sequence: self.sequence,
+                    bid_or_ask,
+                    price,
+                    size: level.map_or(dec!(0), |level| level.size),
+                    order_count: level.map_or(0, |level| level.order_count),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns every level as of the last published update.
+    pub fn snapshot(&self) -> L2Snapshot {
+        L2Snapshot {
+            sequence: self.sequence,
+            depth: Depth {
+                bids: self.bids.values().rev().cloned().collect(),
+                asks: self.asks.values().cloned().collect(),
+            },
+        }
+    }
+
+    fn apply(&mut self, log: &dyn Log, touched: &mut Vec<(BidOrAsk, Decimal)>) {
+        let any = log.as_any();
+        if let Some(log) = any.downcast_ref::<OpenLog>() {
+            // A new order, or the next slice of an iceberg that was already resting.
+            touch(touched, self.remove(&log.order_id));
+            touch(touched, Some(self.add(log.order_id.clone(), log.bid_or_ask.clone(), log.price, log.size)));
+        } else if let Some(log) = any.downcast_ref::<MatchLog>() {
+            touch(touched, self.reduce(&log.maker_order_id, log.size));
+        } else if let Some(log) = any.downcast_ref::<AmendLog>() {
+            touch(touched, self.remove(&log.order_id));
+            touch(touched, Some(self.add(log.order_id.clone(), log.bid_or_ask.clone(), log.price, log.size)));
+        } else if let Some(log) = any.downcast_ref::<DoneLog>() {
+            touch(touched, self.remove(&log.order_id)); // Orders that never rested are not tracked.
+        }
+    }
+
+    fn add(&mut self, order_id: String, bid_or_ask: BidOrAsk, price: Decimal, size: Decimal) -> (BidOrAsk, Decimal) {
+        let level = self
+            .levels_mut(&bid_or_ask)
+            .entry(price)
+            .or_insert(PriceLevel { price, size: dec!(0), order_count: 0 });
+        level.size += size;
+        level.order_count += 1;
+        self.orders.insert(order_id, RestingOrder { bid_or_ask: bid_or_ask.clone(), price, size });
+        (bid_or_ask, price)
+    }
+
+    fn reduce(&mut self, order_id: &str, size: Decimal) -> Option<(BidOrAsk, Decimal)> {
+        let order = self.orders.get_mut(order_id)?;
+        order.size -= size;
+        let (bid_or_ask, price) = (order.bid_or_ask.clone(), order.price);
+        if let Some(level) = self.levels_mut(&bid_or_ask).get_mut(&price) {
+            level.size -= size;
+        }
+        Some((bid_or_ask, price))
+    }
+
+    fn remove(&mut self, order_id: &str) -> Option<(BidOrAsk, Decimal)> {
+        let order = self.orders.remove(order_id)?;
+        let levels = self.levels_mut(&order.bid_or_ask);
+        if let Some(level) = levels.get_mut(&order.price) {
+            level.size -= order.size;
+            level.order_count -= 1;
+            if level.order_count == 0 {
+                levels.remove(&order.price);
+            }
+        }
+        Some((order.bid_or_ask, order.price))
+    }
+
+    fn levels(&self, bid_or_ask: &BidOrAsk) -> &BTreeMap<Decimal, PriceLevel> {
+        match bid_or_ask {
+            BidOrAsk::Bid => &self.bids,
+            BidOrAsk::Ask => &self.asks,
+        }
+    }
+
+    fn levels_mut(&mut self, bid_or_ask: &BidOrAsk) -> &mut BTreeMap<Decimal, PriceLevel> {
+        match bid_or_ask {
+            BidOrAsk::Bid => &mut self.bids,
+            BidOrAsk::Ask => &mut self.asks,
+        }
+    }
+}
+
+/// Records a changed level once, keeping the order in which levels were first changed.
+fn touch(touched: &mut Vec<(BidOrAsk, Decimal)>, level: Option<(BidOrAsk, Decimal)>) {
+    if let Some(level) = level {
+        if !touched.contains(&level) {
+            touched.push(level);
+        }
+    }
+}
+
+/// A client-side copy of the level-2 book, kept up to date from `LevelUpdate`s.
+#[derive(Debug, Clone, Default)]
+pub struct L2Book {
+    bids: BTreeMap<Decimal, PriceLevel>, // The bid levels.
+    asks: BTreeMap<Decimal, PriceLevel>, // The ask levels.
+    sequence: i64,                       // The sequence of the last update applied.
+}
+
+impl L2Book {
+    /// Creates the book from a snapshot of the publisher.
+    pub fn from_snapshot(snapshot: L2Snapshot) -> Self {
+        let side = |levels: Vec<PriceLevel>| levels.into_iter().map(|level| (level.price, level)).collect();
+        Self {
+            bids: side(snapshot.depth.bids),
+            asks: side(snapshot.depth.asks),
+            sequence: snapshot.sequence,
+        }
+    }
+
+    /// Returns the sequence of the last update applied.
+    pub fn sequence(&self) -> i64 {
+        self.sequence
+    }
+
+    /// Applies an update.
+    ///
+    /// Updates already covered by the book, such as those buffered while fetching the snapshot,
+    /// are ignored.
+    ///
+    /// # Returns
+    /// * `Ok(())`, or `FeedError::SequenceGap` if updates were missed; the book is left unchanged
+    ///   and must be bootstrapped again.
+    pub fn apply(&mut self, update: &LevelUpdate) -> Result<(), FeedError> {
+        if !check_sequence(self.sequence, update.sequence)? {
+            return Ok(());
+        }
+
+        let levels = match update.bid_or_ask {
+            BidOrAsk::Bid => &mut self.bids,
+            BidOrAsk::Ask => &mut self.asks,
+        };
+        if update.order_count == 0 {
+            levels.remove(&update.price);
+        } else {
+            levels.insert(
+                update.price,
+                PriceLevel {
+                    price: update.price,
+                    size: update.size,
+                    order_count: update.order_count,
+                },
+            );
+        }
+        self.sequence = update.sequence;
+        Ok(())
+    }
+
+    /// Returns up to `n` levels per side, best price first.
+    pub fn depth(&self, n: usize) -> Depth {
+        Depth {
+            bids: self.bids.values().rev().take(n).cloned().collect(),
+            asks: self.asks.values().take(n).cloned().collect(),
+        }
+    }
+}
@@ -0,0 +1,307 @@
+#![allow(dead_code)]
+use crate::core::engine::Event;
+use crate::core::error::{check_sequence, FeedError};
+use crate::core::log::{AmendLog, DoneLog, Log, MatchLog, OpenLog};
+use crate::core::order::BidOrAsk;
+use crate::core::order_book::OrderBook;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+
+/// A change to a single order, as published on the level-3 feed.
+///
+/// Sizes are visible sizes; the hidden reserve of an iceberg order is never published. Queue
+/// positions count from zero at the front of the order's price level.
+#[derive(Debug, Clone, PartialEq)]
+pub enum L3Event {
+    /// An order started resting at the given queue position.
+    Add { order_id: String, bid_or_ask: BidOrAsk, price: Decimal, size: Decimal, position: usize },
+    /// A resting order changed price or size, or showed a new iceberg slice, and now rests at the
+    /// given queue position.
+    Modify { order_id: String, bid_or_ask: BidOrAsk, price: Decimal, size: Decimal, position: usize },
+    /// A resting order left the book from the given queue position.
+    Delete { order_id: String, bid_or_ask: BidOrAsk, price: Decimal, position: usize },
+    /// A resting order traded `size` in trade `trade_id` and has `remaining` left in place.
+    Execute {
+        order_id: String,
+        bid_or_ask: BidOrAsk,
+        price: Decimal,
+        trade_id: i64,
+        size: Decimal,
+        remaining: Decimal,
+        position: usize,
+    },
+}
+
+/// An `L3Event` with its position in the feed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct L3Update {
+    pub sequence: i64,  // The position of the update in the feed, without gaps.
+    pub event: L3Event, // The change to the order.
+}
+
+/// A resting order as seen on the level-3 feed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct L3Order {
+    pub order_id: String,
+    pub bid_or_ask: BidOrAsk,
+    pub price: Decimal,
+    pub size: Decimal,   // The visible size.
+    pub position: usize, // The queue position within the price level.
+}
+
+// Where a resting order sits, and how much of it is visible
+#[derive(Debug, Clone)]
+struct Entry {
+    bid_or_ask: BidOrAsk,
+    price: Decimal,
+    size: Decimal,
+}
+
+/// A reference order-by-order book, rebuilt from `L3Update`s alone.
+///
+/// It holds every resting order in queue order and can be compared with the live `OrderBook`.
+#[derive(Debug, Clone, Default)]
+pub struct L3Book {
+    orders: HashMap<String, Entry>,       // The resting orders, by ID.
+    bids: BTreeMap<Decimal, Vec<String>>, // The bid queues, each front first.
+    asks: BTreeMap<Decimal, Vec<String>>, // The ask queues, each front first.
+    sequence: i64,                        // The sequence of the last update applied.
+}
+
+impl L3Book {
+    /// Creates an empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the sequence of the last update applied.
+    pub fn sequence(&self) -> i64 {
+        self.sequence
+    }
+
+    /// Applies an update to the order it names, placing the order at the queue position it
+    /// carries.
+    ///
+    /// Updates at or below `sequence`, e.g. those a client buffered while cloning the publisher's
+    /// `snapshot`, are skipped.
+    ///
+    /// # Returns
+    /// * `Ok(())`, or `FeedError::SequenceGap` if updates were missed. Queue positions cannot be
+    ///   trusted after a missed update, so the book is left as it was and should be replaced by
+    ///   a fresh snapshot.
+    pub fn apply(&mut self, update: &L3Update) -> Result<(), FeedError> {
+        if !check_sequence(self.sequence, update.sequence)? {
+            return Ok(());
+        }
+        self.apply_event(&update.event);
+        self.sequence = update.sequence;
+        Ok(())
+    }
+
+    /// Returns the resting orders: asks (best price outwards), then bids (best price outwards),
+    /// each level in queue order.
+    pub fn orders(&self) -> Vec<L3Order> {
+        let asks = self.asks.values();
+        let bids = self.bids.values().rev();
+        asks.chain(bids)
+            .flat_map(|queue| queue.iter().enumerate())
+            .map(|(position, order_id)| {
+                let entry = &self.orders[order_id];
+                L3Order {
+                    order_id: order_id.clone(),
+                    bid_or_ask: entry.bid_or_ask.clone(),
+                    price: entry.price,
+                    size: entry.size,
+                    position,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns an order, if it is resting.
+    pub fn get_order(&self, order_id: &str) -> Option<L3Order> {
+        let entry = self.orders.get(order_id)?;
+        Some(L3Order {
+            order_id: order_id.to_string(),
+            bid_or_ask: entry.bid_or_ask.clone(),
+            price: entry.price,
+            size: entry.size,
+            position: self.position(order_id)?,
+        })
+    }
+
+    fn apply_event(&mut self, event: &L3Event) {
+        match event {
+            L3Event::Add { order_id, bid_or_ask, price, size, position }
+            | L3Event::Modify { order_id, bid_or_ask, price, size, position } => {
+                self.remove(order_id);
+                let queue = self.queues_mut(bid_or_ask).entry(*price).or_default();
+                queue.insert((*position).min(queue.len()), order_id.clone());
+                let entry = Entry { bid_or_ask: bid_or_ask.clone(), price: *price, size: *size };
+                self.orders.insert(order_id.clone(), entry);
+            }
+            L3Event::Delete { order_id, .. } => self.remove(order_id),
+            L3Event::Execute { order_id, remaining, .. } => {
+                if let Some(entry) = self.orders.get_mut(order_id) {
+                    entry.size = *remaining;
+                }
+            }
+        }
+    }
+
+    fn position(&self, order_id: &str) -> Option<usize> {
+        let entry = self.orders.get(order_id)?;
+        self.queues(&entry.bid_or_ask)
+            .get(&entry.price)?
+            .iter()
+            .position(|id| id == order_id)
+    }
+
+    fn queue_len(&self, bid_or_ask: &BidOrAsk, price: Decimal) -> usize {
+        self.queues(bid_or_ask).get(&price).map_or(0, Vec::len)
+    }
+
+    fn remove(&mut self, order_id: &str) {
+        let Some(entry) = self.orders.remove(order_id) else {
+            return;
+        };
+        let queues = self.queues_mut(&entry.bid_or_ask);
+        if let Some(queue) = queues.get_mut(&entry.price) {
+            queue.retain(|id| id != order_id);
+            if queue.is_empty() {
+                queues.remove(&entry.price);
+            }
+        }
+    }
+
+    fn queues(&self, bid_or_ask: &BidOrAsk) -> &BTreeMap<Decimal, Vec<String>> {
+        match bid_or_ask {
+            BidOrAsk::Bid => &self.bids,
+            BidOrAsk::Ask => &self.asks,
+        }
+    }
+
+    fn queues_mut(&mut self, bid_or_ask: &BidOrAsk) -> &mut BTreeMap<Decimal, Vec<String>> {
+        match bid_or_ask {
+            BidOrAsk::Bid => &mut self.bids,
+            BidOrAsk::Ask => &mut self.asks,
+        }
+    }
+}
+
+/// Turns the logs of an engine into level-3 updates.
+///
+/// The publisher keeps its own `L3Book` to work out queue positions: an order that is added,
+/// moved to another price, grown or refreshed goes to the back of its level, while one that is
+/// only reduced keeps its place. A client bootstraps by buffering updates, cloning `snapshot` and
+/// applying the buffered updates to the clone.
+#[derive(Debug, Default)]
+pub struct L3Publisher {
+    book: L3Book, // The orders as published so far.
+}
+
+impl L3Publisher {
+    /// Creates a publisher for an empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a publisher seeded with the orders resting in `book`, each at its current queue
+    /// position, e.g. after the book was restored from a snapshot. Seeding publishes nothing, so
+    /// the feed still starts at sequence zero; every log the book produces from now on must be
+    /// passed to `publish`.
+    pub fn from_book(book: &OrderBook) -> Self {
+        let mut publisher = Self::new();
+        for limit in book.ask_limits().chain(book.bid_limits()) {
+            for (position, order) in limit.orders.iter().enumerate() {
+                publisher.book.apply_event(&L3Event::Add {
+                    order_id: order.id.clone(),
+                    bid_or_ask: order.bid_or_ask.clone(),
+                    price: order.price,
+                    size: order.size,
+                    position,
+                });
+            }
+        }
+        publisher
+    }
+
+    /// Returns a copy of every published order in queue order, as of the last published update.
+    /// Its `sequence` tells a client which buffered updates the copy already covers.
+    pub fn snapshot(&self) -> L3Book {
+        self.book.clone()
+    }
+
+    /// Converts the logs among `events` into updates.
+    ///
+    /// # Arguments
+    /// * `events` - The events returned by `Engine::process`, in order.
+    ///
+    /// # Returns
+    /// * One `L3Update` per change to a resting order, in the order the changes happened.
+    pub fn publish(&mut self, events: &[Event]) -> Vec<L3Update> {
+        let mut updates = vec![];
+        for event in events {
+            let Event::Log(log) = event else {
+                continue;
+            };
+            if let Some(event) = self.convert(log.as_ref()) {
+                self.book.apply_event(&event);
+                self.book.sequence += 1;
+                updates.push(L3Update { sequence: self.book.sequence, event });
+            }
+        }
+        updates
+    }
+
+    fn convert(&self, log: &dyn Log) -> Option<L3Event> {
+        let any = log.as_any();
+        if let Some(log) = any.downcast_ref::<OpenLog>() {
+            // The next slice of a resting iceberg goes to the back of the queue, like a new order.
+            let resting = self.book.get_order(&log.order_id);
+            let position = self.book.queue_len(&log.bid_or_ask, log.price) - resting.is_some() as usize;
+            let (order_id, bid_or_ask, price, size) = (log.order_id.clone(), log.bid_or_ask.clone(), log.price, log.size);
+            return Some(match resting {
+                Some(_) => L3Event::Modify { order_id, bid_or_ask, price, size, position },
+                None => L3Event::Add { order_id, bid_or_ask, price, size, position },
+            });
+        }
+        if let Some(log) = any.downcast_ref::<MatchLog>() {
+            let maker = self.book.get_order(&log.maker_order_id)?;
+            return Some(L3Event::Execute {
+                order_id: maker.order_id,
+                bid_or_ask: maker.bid_or_ask,
+                price: maker.price,
+                trade_id: log.trade_id,
+                size: log.size,
+                remaining: maker.size - log.size,
+                position: maker.position,
+            });
+        }
+        if let Some(log) = any.downcast_ref::<AmendLog>() {
+            let resting = self.book.get_order(&log.order_id)?;
+            let position = match log.keeps_priority {
+                true => resting.position,
+                false if resting.price == log.price => self.book.queue_len(&log.bid_or_ask, log.price) - 1,
+                false => self.book.queue_len(&log.bid_or_ask, log.price),
+            };
+            return Some(L3Event::Modify {
+                order_id: log.order_id.clone(),
+                bid_or_ask: log.bid_or_ask.clone(),
+                price: log.price,
+                size: log.size,
+                position,
+            });
+        }
+        if let Some(log) = any.downcast_ref::<DoneLog>() {
+            let resting = self.book.get_order(&log.order_id)?; // Orders that never rested are not published.
+            return Some(L3Event::Delete {
+                order_id: resting.order_id,
+                bid_or_ask: resting.bid_or_ask,
+                price: resting.price,
+                position: resting.position,
+            });
+        }
+        None
+    }
+}
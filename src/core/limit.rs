@@ -1,7 +1,8 @@
 #![allow(dead_code)]
 
-use crate::core::log::{DoneLog, Log, MatchLog, OpenLog};
-use crate::core::order::Order;
+use crate::core::error::MatchingError;
+use crate::core::log::{AmendLog, DoneLog, Log, MatchLog, OpenLog};
+use crate::core::order::{Order, SelfTradePrevention};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
@@ -14,6 +15,15 @@ use rust_decimal_macros::dec;
 pub struct Limit {
     pub(crate) price: Decimal,     // The price for this limit order.
     pub(crate) orders: Vec<Order>, // A list of orders associated with this limit price.
+    next_position: u64,            // The time-priority ticket handed to the next added order.
+}
+
+/// The outcome of matching an incoming order against the resting orders of a single `Limit`.
+pub(crate) struct FillResult {
+    pub(crate) logs: Vec<Box<dyn Log>>,      // Logs for matches, refreshes and removed resting orders.
+    pub(crate) filled: Vec<String>,          // IDs of resting orders that were removed from the limit.
+    pub(crate) requeued: Vec<(String, u64)>, // IDs and new positions of refreshed iceberg orders.
+    pub(crate) traded: bool,                 // Whether at least one match took place.
 }
 
 impl Limit {
@@ -28,26 +38,66 @@ impl Limit {
         Limit {
             price,
             orders: Vec::new(),
+            next_position: 0,
         }
     }
 
     /// Calculates the total volume of all orders associated with this limit order.
     ///
-    /// The total volume is computed by summing the size of each order in the `orders` list.
+    /// The total volume is computed by summing the size of each order in the `orders` list. Only
+    /// the displayed slice of iceberg orders is counted, so this is the volume visible in depth.
     ///
     /// # Returns
-    /// * The total volume as a `Decimal`.
-    fn total_volume(&self) -> Decimal {
+    /// * The total volume as a `Decimal`, or zero if the limit has no orders.
+    pub(crate) fn total_volume(&self) -> Decimal {
         self.orders
             .iter()
             .map(|order| order.size) // Summing the size of each order.
-            .reduce(|a, b| a + b) // Reducing the sizes to get the total volume.
-            .unwrap() // This will panic if the list is empty.
+            .sum()
+    }
+
+    /// Calculates the volume that can be traded at this limit, including hidden iceberg reserves.
+    ///
+    /// # Returns
+    /// * The available volume as a `Decimal`.
+    pub(crate) fn available_volume(&self) -> Decimal {
+        self.orders.iter().map(|order| order.total_size()).sum()
+    }
+
+    /// Calculates the volume an incoming order could trade at this limit under self-trade
+    /// prevention.
+    ///
+    /// Orders of the incoming order's owner never trade with it while a mode is set. They are
+    /// skipped, except under `CancelNewest` and `CancelBoth`, where reaching one cancels the
+    /// incoming order: only the visible size queued ahead of it can be traded.
+    ///
+    /// # Arguments
+    /// * `owner` - The owner of the incoming order, if any.
+    /// * `self_trade_prevention` - The self-trade prevention mode, or `None` to allow self trades.
+    ///
+    /// # Returns
+    /// * The tradable volume, and `true` if matching would stop at this limit.
+    pub(crate) fn tradable_volume(
+        &self,
+        owner: Option<&String>,
+        self_trade_prevention: Option<&SelfTradePrevention>,
+    ) -> (Decimal, bool) {
+        let own = |order: &Order| self_trade_prevention.is_some() && owner.is_some() && order.owner.as_ref() == owner;
+        let stops = matches!(
+            self_trade_prevention,
+            Some(SelfTradePrevention::CancelNewest | SelfTradePrevention::CancelBoth)
+        );
+        match self.orders.iter().position(own) {
+            Some(index) if stops => (self.orders[..index].iter().map(|order| order.size).sum(), true),
+            _ => (self.orders.iter().filter(|order| !own(order)).map(Order::total_size).sum(), false),
+        }
     }
 
     /// Adds a new order to the limit order book and generates an `OpenLog` entry.
     ///
-    /// The order is pushed into the `orders` list, and an `OpenLog` is generated for the action.
+    /// The order is stamped with the next time-priority position and pushed to the back of the
+    /// `orders` list, and an `OpenLog` is generated for the action. An iceberg order only shows
+    /// its display size; the rest of its size is held back as a hidden reserve.
     ///
     /// # Arguments
     /// * `order` - An `Order` object to be added to the limit order book.
@@ -56,16 +106,104 @@ impl Limit {
     /// # Returns
     /// * An `OpenLog` representing the addition of the order.
     pub(crate) fn add_order(&mut self, order: Order, sequence: i64) -> OpenLog {
-        self.orders.push(order.clone());
+        let order = self.push_order(order);
         OpenLog::new(
             sequence, // Use the sequence from OrderBook
-            order.id,
+            order.id.clone(),
             order.size,
             order.price,
-            order.bid_or_ask,
+            order.bid_or_ask.clone(),
         )
     }
 
+    /// Puts back an order captured by a snapshot, without generating a log.
+    ///
+    /// The order keeps its time-priority position, so orders must be restored in queue order.
+    ///
+    /// # Arguments
+    /// * `order` - The `Order` as it rested when the snapshot was taken.
+    pub(crate) fn restore_order(&mut self, order: Order) {
+        self.next_position = self.next_position.max(order.position + 1);
+        self.orders.push(order);
+    }
+
+    /// Moves an amended order to the back of the queue and generates an `AmendLog` entry.
+    ///
+    /// The order loses its time priority and is stamped with the next position, exactly as if it
+    /// had just been added.
+    ///
+    /// # Arguments
+    /// * `order` - The amended `Order`, already carrying its new price and size.
+    /// * `old_price` - The price the order rested at before the amendment.
+    /// * `old_size` - The displayed size of the order before the amendment.
+    /// * `sequence` - An `i64`
+    ///
+    /// # Returns
+    /// * An `AmendLog` describing the amendment.
+    pub(crate) fn requeue_order(&mut self, order: Order, old_price: Decimal, old_size: Decimal, sequence: i64) -> AmendLog {
+        let order = self.push_order(order);
+        AmendLog::new(
+            sequence,
+            order.id.clone(),
+            old_price,
+            old_size,
+            order.price,
+            order.size,
+            order.bid_or_ask.clone(),
+            false,
+        )
+    }
+
+    /// Reduces the size of an order in place and generates an `AmendLog` entry.
+    ///
+    /// The order keeps its time priority. For iceberg orders the hidden reserve is reduced first.
+    ///
+    /// # Arguments
+    /// * `position` - The position assigned to the order when it was added.
+    /// * `new_size` - The new total size, which must not exceed the current total size.
+    /// * `sequence` - An `i64`
+    ///
+    /// # Returns
+    /// * An `AmendLog` describing the amendment, or `None` if no order holds the position.
+    pub(crate) fn reduce_order(&mut self, position: u64, new_size: Decimal, sequence: i64) -> Option<AmendLog> {
+        let index = self
+            .orders
+            .binary_search_by_key(&position, |order| order.position)
+            .ok()?;
+        let order = &mut self.orders[index];
+        let old_size = order.size;
+        order.hidden_size = (new_size - order.size).max(dec!(0));
+        order.size = new_size.min(order.size);
+
+        Some(AmendLog::new(
+            sequence,
+            order.id.clone(),
+            order.price,
+            old_size,
+            order.price,
+            order.size,
+            order.bid_or_ask.clone(),
+            true,
+        ))
+    }
+
+    /// Stamps an order with the next time-priority position and pushes it to the back of the queue.
+    ///
+    /// An iceberg order only shows its display size; the rest of its size is held back as a
+    /// hidden reserve.
+    fn push_order(&mut self, mut order: Order) -> &Order {
+        if let Some(display_size) = order.display_size {
+            if display_size > dec!(0) && order.size > display_size {
+                order.hidden_size += order.size - display_size; // Hide everything beyond the display size.
+                order.size = display_size;
+            }
+        }
+        order.position = self.next_position;
+        self.next_position += 1;
+        self.orders.push(order);
+        &self.orders[self.orders.len() - 1]
+    }
+
     /// Deletes an order by its ID and generates a `DoneLog` entry for the removal.
     ///
     /// The order is removed from the `orders` list, and a `DoneLog` is generated to indicate the
@@ -76,99 +214,275 @@ impl Limit {
     /// * `sequence` - An `i64`
     ///
     /// # Returns
-    /// * A `DoneLog` representing the deletion of the order.
-    pub(crate) fn delete_order(&mut self, id: String, sequence: i64) -> DoneLog {
-        let order = self
+    /// * A `DoneLog` representing the deletion of the order, or `MatchingError::UnknownOrder` if
+    ///   no order at this limit has the given ID.
+    pub(crate) fn delete_order(&mut self, id: String, sequence: i64) -> Result<DoneLog, MatchingError> {
+        let index = self
             .orders
             .iter()
-            .find(|&order| order.id == id) // Find the order by its ID.
-            .unwrap() // Will panic if no matching order is found.
-            .clone();
-        self.orders.retain(|order| order.id != id); // Remove the order from the list.
+            .position(|order| order.id == id) // Find the order by its ID.
+            .ok_or_else(|| MatchingError::UnknownOrder(id.clone()))?;
+        let order = self.orders.remove(index); // Remove the order from the list.
 
-        DoneLog::new(
+        Ok(DoneLog::new(
             sequence,              // Sequence number for the log.
             id,                    // The ID of the deleted order.
             order.price,           // The price of the deleted order.
             order.size,            // The size of the deleted order.
             "DELETED".to_string(), // Status indicating the order was deleted.
             order.bid_or_ask,      // The type of the deleted order (bid or ask).
-        )
+        ))
+    }
+
+    /// Finds the order holding the given time-priority position.
+    ///
+    /// Orders are kept in ascending position order, so the lookup is a binary search.
+    ///
+    /// # Arguments
+    /// * `position` - The position assigned to the order when it was added.
+    ///
+    /// # Returns
+    /// * The order, or `None` if no order at this limit holds the position.
+    pub(crate) fn find_order(&self, position: u64) -> Option<&Order> {
+        self.orders
+            .binary_search_by_key(&position, |order| order.position)
+            .ok()
+            .map(|index| &self.orders[index])
+    }
+
+    /// Removes the order holding the given time-priority position and generates a `DoneLog` entry.
+    ///
+    /// # Arguments
+    /// * `position` - The position assigned to the order when it was added.
+    /// * `reason` - The reason recorded in the `DoneLog`, such as `CANCELED` or `EXPIRED`.
+    /// * `sequence` - An `i64`
+    ///
+    /// # Returns
+    /// * A `DoneLog` for the removed order, or `None` if no order holds the position.
+    pub(crate) fn cancel_order(&mut self, position: u64, reason: &str, sequence: i64) -> Option<DoneLog> {
+        let order = self.take_order(position)?;
+
+        Some(DoneLog::new(
+            sequence,
+            order.id.clone(),
+            order.price,
+            order.total_size(),
+            reason.to_string(),
+            order.bid_or_ask,
+        ))
+    }
+
+    /// Removes the order holding the given time-priority position without generating a log.
+    ///
+    /// # Arguments
+    /// * `position` - The position assigned to the order when it was added.
+    ///
+    /// # Returns
+    /// * The removed order, or `None` if no order holds the position.
+    pub(crate) fn take_order(&mut self, position: u64) -> Option<Order> {
+        let index = self
+            .orders
+            .binary_search_by_key(&position, |order| order.position)
+            .ok()?;
+        Some(self.orders.remove(index))
     }
 
     /// Fills a market order by matching it with limit orders at this price level.
     ///
     /// This function attempts to match a given market order against the current limit orders.
-    /// It iterates through the limit orders and performs the following steps:
+    /// It iterates through the limit orders in time priority and performs the following steps:
     /// - If the size of the market order is greater than or equal to the size of a limit order,
     ///   the limit order is fully filled, and the remaining size of the market order is reduced.
     /// - If the size of the market order is smaller than a limit order, the market order is fully filled,
     ///   and the remaining size of the limit order is reduced.
     ///
+    /// When the displayed slice of an iceberg order is consumed and a hidden reserve remains, a new
+    /// slice is shown and the order moves to the back of the queue instead of being removed.
+    ///
+    /// When both orders belong to the same owner and a self-trade prevention mode is given, no match
+    /// takes place. Instead, the orders selected by the mode are canceled (or decremented) and a
+    /// `DoneLog` with an `STP_*` reason is generated for each canceled order. A canceled market order
+    /// has its size set to zero.
+    ///
     /// During the matching process, logs are generated for each match and for any remaining open orders.
-    /// These logs include `MatchLog` for matched orders and `DoneLog` for orders that is filled after
-    /// processing.
+    /// These logs include `MatchLog` for matched orders, `OpenLog` for refreshed iceberg slices and
+    /// `DoneLog` for orders that is filled after processing.
     ///
     /// # Arguments
     /// * `market_order` - A mutable reference to the market `Order` to be filled.
-    /// * `sequence` - An `i64`
+    /// * `self_trade_prevention` - The self-trade prevention mode, or `None` to allow self trades.
+    /// * `sequence` - The sequence of the last log, advanced for every log produced.
+    /// * `trade_sequence` - The ID of the last trade, advanced for every `MatchLog`.
     ///
     /// # Returns
-    /// * A `Vec<dyn Log>` containing logs of matches and filleds orders.
+    /// * A `FillResult` containing logs of matches and filled orders, along with the IDs of the
+    ///   resting orders that were removed or moved to the back of the queue.
     ///
     /// # Behavior
     /// - Fully filled limit orders are removed from the order book.
     /// - Limit orders with remaining size after matching are retained.
+    /// - Iceberg orders with a hidden reserve are refreshed and requeued.
+    /// - Matching stops as soon as the market order is filled.
     /// - The function generates logs for matched orders and done orders.
     ///
     /// # Logs
-    /// * `MatchLog` is generated when a match occurs between a market order and a limit order.
-    /// * `DoneLog` is generated for orders that remain in the order book after processing.
+    /// * `MatchLog` is generated when a match occurs between a market order and a limit order. It
+    ///   carries the executed quantity at the limit price and the sizes both orders have left.
+    /// * `OpenLog` is generated when an iceberg order shows a new slice.
+    /// * `DoneLog` is generated for orders that remain in the order book after processing, and for
+    ///   orders canceled by self-trade prevention.
+    /// * `AmendLog` is generated for a resting order decremented by self-trade prevention that keeps
+    ///   some size, so the change is visible to consumers of the logs.
     pub(crate) fn fill_order(
         &mut self,
         market_order: &mut Order,
-        sequence: i64,
-    ) -> Vec<Box<dyn Log>> {
+        self_trade_prevention: Option<&SelfTradePrevention>,
+        sequence: &mut i64,
+        trade_sequence: &mut i64,
+    ) -> FillResult {
+        let mut next_sequence = || {
+            *sequence += 1;
+            *sequence
+        };
         let mut logs: Vec<Box<dyn Log>> = vec![];
-        let mut remove_indices = Vec::new();
-        for (idx, limit_order) in self.orders.iter_mut().enumerate() {
-            logs.push(Box::new(MatchLog::new(
-                sequence,
-                market_order.clone().id,
-                limit_order.clone().id,
-                limit_order.price,
-                market_order.size,
-            )));
-            match market_order.size >= limit_order.size {
-                true => {
-                    market_order.size -= limit_order.size;
+        let mut filled = Vec::new();
+        let mut requeued = Vec::new();
+        let mut traded = false;
+        let mut index = 0;
+        while index < self.orders.len() && !market_order.is_filled() {
+            let limit_order = &mut self.orders[index];
+            let self_trade = match (&market_order.owner, &limit_order.owner) {
+                (Some(taker), Some(maker)) if taker == maker => self_trade_prevention,
+                _ => None,
+            };
 
-                    limit_order.size = dec!(0); // Fully filled limit order.
+            match self_trade {
+                Some(SelfTradePrevention::CancelNewest) => {
+                    logs.push(Box::new(stp_done_log(market_order, market_order.size, "STP_CANCEL_NEWEST", next_sequence())));
+                    market_order.size = dec!(0);
+                    break;
                 }
-                false => {
-                    limit_order.size -= market_order.size;
-                    market_order.size = dec!(0); // Fully filled market order.
+                Some(mode @ (SelfTradePrevention::CancelOldest | SelfTradePrevention::CancelBoth)) => {
+                    let reason = match mode {
+                        SelfTradePrevention::CancelOldest => "STP_CANCEL_OLDEST",
+                        _ => "STP_CANCEL_BOTH",
+                    };
+                    let order = self.orders.remove(index);
+                    logs.push(Box::new(stp_done_log(&order, order.total_size(), reason, next_sequence())));
+                    filled.push(order.id);
+                    if *mode == SelfTradePrevention::CancelBoth {
+                        logs.push(Box::new(stp_done_log(market_order, market_order.size, reason, next_sequence())));
+                        market_order.size = dec!(0);
+                        break;
+                    }
+                    continue;
                 }
+                Some(SelfTradePrevention::DecrementAndCancel) => {
+                    // Shrink both orders without trading, taking from the hidden reserve first.
+                    let decrement = market_order.size.min(limit_order.total_size());
+                    let from_hidden = decrement.min(limit_order.hidden_size);
+                    let old_size = limit_order.size;
+                    limit_order.hidden_size -= from_hidden;
+                    limit_order.size -= decrement - from_hidden;
+                    market_order.size -= decrement;
+
+                    if limit_order.total_size() == dec!(0) {
+                        let order = self.orders.remove(index);
+                        logs.push(Box::new(stp_done_log(&order, dec!(0), "STP_DECREMENT", next_sequence())));
+                        filled.push(order.id);
+                    } else {
+                        logs.push(Box::new(AmendLog::new(
+                            next_sequence(),
+                            limit_order.id.clone(),
+                            limit_order.price,
+                            old_size,
+                            limit_order.price,
+                            limit_order.size,
+                            limit_order.bid_or_ask.clone(),
+                            true,
+                        )));
+                        index += 1;
+                    }
+                    if market_order.is_filled() {
+                        logs.push(Box::new(stp_done_log(market_order, dec!(0), "STP_DECREMENT", next_sequence())));
+                        break;
+                    }
+                    continue;
+                }
+                None => {}
             }
-            if limit_order.is_filled() {
-                remove_indices.push(idx);
-            }
-        }
 
-        for index in remove_indices.iter().rev() {
-            let order_ref: &Order = &self.orders[*index];
-            logs.push(Box::new(DoneLog::new(
-                sequence,
-                order_ref.id.clone(),
-                order_ref.price,
-                dec!(0),
-                "FILLED".to_string(),
-                order_ref.bid_or_ask.clone(),
+            let size = market_order.size.min(limit_order.size);
+            market_order.size -= size;
+            limit_order.size -= size;
+            *trade_sequence += 1;
+            logs.push(Box::new(MatchLog::new(
+                next_sequence(),
+                *trade_sequence,
+                market_order.id.clone(),
+                limit_order.id.clone(),
+                market_order.bid_or_ask.clone(),
+                limit_order.price,
+                size,
+                market_order.size,
+                limit_order.total_size(),
             )));
+            traded = true;
+            if !limit_order.is_filled() {
+                index += 1;
+                continue;
+            }
 
-            self.orders.remove(*index);
+            let mut order = self.orders.remove(index);
+            if order.hidden_size > dec!(0) {
+                // Show the next slice of the iceberg and send it to the back of the queue.
+                let slice = match order.display_size {
+                    Some(display_size) if display_size > dec!(0) => display_size.min(order.hidden_size),
+                    _ => order.hidden_size,
+                };
+                order.size = slice;
+                order.hidden_size -= slice;
+                order.position = self.next_position;
+                self.next_position += 1;
+                logs.push(Box::new(OpenLog::new(
+                    next_sequence(),
+                    order.id.clone(),
+                    order.size,
+                    order.price,
+                    order.bid_or_ask.clone(),
+                )));
+                requeued.push((order.id.clone(), order.position));
+                self.orders.push(order);
+            } else {
+                logs.push(Box::new(DoneLog::new(
+                    next_sequence(),
+                    order.id.clone(),
+                    order.price,
+                    dec!(0),
+                    "FILLED".to_string(),
+                    order.bid_or_ask.clone(),
+                )));
+                filled.push(order.id);
+            }
         }
 
-        logs
+        FillResult {
+            logs,
+            filled,
+            requeued,
+            traded,
+        }
     }
 }
+
+/// Builds the `DoneLog` for an order canceled by self-trade prevention.
+fn stp_done_log(order: &Order, remaining_size: Decimal, reason: &str, sequence: i64) -> DoneLog {
+    DoneLog::new(
+        sequence,
+        order.id.clone(),
+        order.price,
+        remaining_size,
+        reason.to_string(),
+        order.bid_or_ask.clone(),
+    )
+}
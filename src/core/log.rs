@@ -1,7 +1,10 @@
 #![allow(dead_code)]
+use std::any::Any;
 use std::time::SystemTime;
 use rust_decimal::Decimal;
-use crate::core::order::BidOrAsk;
+use crate::core::codec::{Reader, Writer};
+use crate::core::error::StorageError;
+use crate::core::order::{BidOrAsk, OrderType};
 
 // Enum for log types
 #[derive(Debug, Clone, Copy)]
@@ -9,11 +12,117 @@ enum LogType {
     Match,
     Open,
     Done,
+    Reject,
+    Trigger,
+    Amend,
+    Received,
+}
+
+impl LogType {
+    fn tag(&self) -> u8 {
+        match self {
+            LogType::Match => 0,
+            LogType::Open => 1,
+            LogType::Done => 2,
+            LogType::Reject => 3,
+            LogType::Trigger => 4,
+            LogType::Amend => 5,
+            LogType::Received => 6,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self, StorageError> {
+        match tag {
+            0 => Ok(LogType::Match),
+            1 => Ok(LogType::Open),
+            2 => Ok(LogType::Done),
+            3 => Ok(LogType::Reject),
+            4 => Ok(LogType::Trigger),
+            5 => Ok(LogType::Amend),
+            6 => Ok(LogType::Received),
+            tag => Err(StorageError::Corrupt(format!("unknown log type {}", tag))),
+        }
+    }
 }
 
 // Trait for logs (equivalent to the abstract base class in C++)
-pub(crate) trait Log {
+pub(crate) trait Log: std::fmt::Debug {
     fn get_seq(&self) -> i64;
+
+    // Gives access to the concrete log type, e.g. `log.as_any().downcast_ref::<MatchLog>()`
+    fn as_any(&self) -> &dyn Any;
+
+    // Writes the log in the engine's binary format; `decode_log` reads it back
+    fn encode(&self, writer: &mut Writer);
+}
+
+/// Reads a log written by `Log::encode`.
+///
+/// # Returns
+/// * The log, or `StorageError::Corrupt` if the bytes do not hold a valid log.
+pub(crate) fn decode_log(reader: &mut Reader) -> Result<Box<dyn Log>, StorageError> {
+    let base = Base::decode(reader)?;
+    Ok(match base.log_type {
+        LogType::Received => Box::new(ReceivedLog {
+            base,
+            order_id: reader.string()?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+            order_type: OrderType::decode(reader)?,
+            size: reader.decimal()?,
+            price: reader.decimal()?,
+        }),
+        LogType::Open => Box::new(OpenLog {
+            base,
+            order_id: reader.string()?,
+            size: reader.decimal()?,
+            price: reader.decimal()?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+        }),
+        LogType::Done => Box::new(DoneLog {
+            base,
+            order_id: reader.string()?,
+            price: reader.decimal()?,
+            remaining_size: reader.decimal()?,
+            reason: reader.string()?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+        }),
+        LogType::Match => Box::new(MatchLog {
+            base,
+            trade_id: reader.i64()?,
+            taker_order_id: reader.string()?,
+            maker_order_id: reader.string()?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+            price: reader.decimal()?,
+            size: reader.decimal()?,
+            taker_remaining: reader.decimal()?,
+            maker_remaining: reader.decimal()?,
+        }),
+        LogType::Reject => Box::new(RejectLog {
+            base,
+            order_id: reader.string()?,
+            size: reader.decimal()?,
+            price: reader.decimal()?,
+            reason: RejectReason::decode(reader)?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+        }),
+        LogType::Trigger => Box::new(TriggerLog {
+            base,
+            order_id: reader.string()?,
+            stop_price: reader.decimal()?,
+            last_price: reader.decimal()?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+        }),
+        LogType::Amend => Box::new(AmendLog {
+            base,
+            order_id: reader.string()?,
+            old_price: reader.decimal()?,
+            old_size: reader.decimal()?,
+            price: reader.decimal()?,
+            size: reader.decimal()?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+            keeps_priority: reader.bool()?,
+        }),
+    })
 }
 
 // Base structure for common fields
@@ -32,26 +141,42 @@ impl Base {
             time,
         }
     }
+
+    fn encode(&self, writer: &mut Writer) {
+        writer.u8(self.log_type.tag());
+        writer.i64(self.sequence);
+        writer.time(self.time);
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, StorageError> {
+        Ok(Base {
+            log_type: LogType::from_tag(reader.u8()?)?,
+            sequence: reader.i64()?,
+            time: reader.time()?,
+        })
+    }
 }
 
-// Derived structure for ReceivedLog
+// Derived structure for ReceivedLog, acknowledging an order as it enters the engine
 #[derive(Debug)]
-struct ReceivedLog {
+pub(crate) struct ReceivedLog {
     base: Base,
-    order_id: String,
-    size: f64,
-    price: f64,
-    order_type: i64,
+    pub(crate) order_id: String,
+    pub(crate) bid_or_ask: BidOrAsk,
+    pub(crate) order_type: OrderType,
+    pub(crate) size: Decimal,
+    pub(crate) price: Decimal, // The limit price; not meaningful for market and stop-market orders.
 }
 
 impl ReceivedLog {
-    fn new(sequence: i64, order_id: String, size: f64, price: f64, order_type: i64) -> Self {
+    pub(crate) fn new(sequence: i64, order_id: String, bid_or_ask: BidOrAsk, order_type: OrderType, size: Decimal, price: Decimal) -> Self {
         ReceivedLog {
-            base: Base::new(LogType::Done, sequence, SystemTime::now()),
+            base: Base::new(LogType::Received, sequence, SystemTime::now()),
             order_id,
+            bid_or_ask,
+            order_type,
             size,
             price,
-            order_type,
         }
     }
 }
@@ -60,6 +185,19 @@ impl Log for ReceivedLog {
     fn get_seq(&self) -> i64 {
         self.base.sequence
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        self.base.encode(writer);
+        writer.string(&self.order_id);
+        self.bid_or_ask.encode(writer);
+        self.order_type.encode(writer);
+        writer.decimal(self.size);
+        writer.decimal(self.price);
+    }
 }
 
 // Derived structure for OpenLog
@@ -67,9 +205,9 @@ impl Log for ReceivedLog {
 pub(crate) struct OpenLog {
     base: Base,
     pub(crate) order_id: String,
-    size: Decimal,
-    price: Decimal,
-    bid_or_ask: BidOrAsk,
+    pub(crate) size: Decimal,
+    pub(crate) price: Decimal,
+    pub(crate) bid_or_ask: BidOrAsk,
 }
 
 impl OpenLog {
@@ -88,17 +226,29 @@ impl Log for OpenLog {
     fn get_seq(&self) -> i64 {
         self.base.sequence
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        self.base.encode(writer);
+        writer.string(&self.order_id);
+        writer.decimal(self.size);
+        writer.decimal(self.price);
+        self.bid_or_ask.encode(writer);
+    }
 }
 
 // Derived structure for DoneLog
 #[derive(Debug)]
 pub(crate) struct DoneLog {
     base: Base,
-    order_id: String,
-    price: Decimal,
-    remaining_size: Decimal,
-    reason: String,
-    bid_or_ask: BidOrAsk,
+    pub(crate) order_id: String,
+    pub(crate) price: Decimal,
+    pub(crate) remaining_size: Decimal,
+    pub(crate) reason: String,
+    pub(crate) bid_or_ask: BidOrAsk,
 }
 
 impl DoneLog {
@@ -118,34 +268,273 @@ impl Log for DoneLog {
     fn get_seq(&self) -> i64 {
         self.base.sequence
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        self.base.encode(writer);
+        writer.string(&self.order_id);
+        writer.decimal(self.price);
+        writer.decimal(self.remaining_size);
+        writer.string(&self.reason);
+        self.bid_or_ask.encode(writer);
+    }
 }
 
 // Derived structure for MatchLog
 #[derive(Debug)]
 pub struct MatchLog {
     base: Base,
-    taker_order_id: String,
-    maker_order_id: String,
-    side: String,
-    price: Decimal,
-    size: Decimal,
+    pub(crate) trade_id: i64,            // The ID of the trade, unique within the book.
+    pub(crate) taker_order_id: String,   // The incoming order.
+    pub(crate) maker_order_id: String,   // The resting order it traded against.
+    pub(crate) bid_or_ask: BidOrAsk,     // The side of the taker.
+    pub(crate) price: Decimal,           // The price of the maker.
+    pub(crate) size: Decimal,            // The executed quantity.
+    pub(crate) taker_remaining: Decimal, // The size left on the taker after the trade.
+    pub(crate) maker_remaining: Decimal, // The size left on the maker after the trade, including any hidden reserve.
 }
 
 impl MatchLog {
-    pub fn new(sequence: i64, taker_order_id: String, maker_order_id: String, price: Decimal, size: Decimal) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        sequence: i64,
+        trade_id: i64,
+        taker_order_id: String,
+        maker_order_id: String,
+        bid_or_ask: BidOrAsk,
+        price: Decimal,
+        size: Decimal,
+        taker_remaining: Decimal,
+        maker_remaining: Decimal,
+    ) -> Self {
         MatchLog {
             base: Base::new(LogType::Match, sequence, SystemTime::now()),
+            trade_id,
             taker_order_id,
             maker_order_id,
-            side: String::from(""),
+            bid_or_ask,
             price,
             size,
+            taker_remaining,
+            maker_remaining,
         }
     }
+
+    /// Returns when the trade was executed.
+    pub(crate) fn time(&self) -> SystemTime {
+        self.base.time
+    }
 }
 
 impl Log for MatchLog {
     fn get_seq(&self) -> i64 {
         self.base.sequence
     }
-}
\ No newline at end of file
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        self.base.encode(writer);
+        writer.i64(self.trade_id);
+        writer.string(&self.taker_order_id);
+        writer.string(&self.maker_order_id);
+        self.bid_or_ask.encode(writer);
+        writer.decimal(self.price);
+        writer.decimal(self.size);
+        writer.decimal(self.taker_remaining);
+        writer.decimal(self.maker_remaining);
+    }
+}
+
+// Machine-readable reasons carried by a RejectLog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    PostOnlyWouldTake,
+    NotAStopOrder,
+    PriceNotOnTick,
+    PricePrecisionExceeded,
+    SizeNotOnLot,
+    SizeBelowMinimum,
+    SizeAboveMaximum,
+    NotionalBelowMinimum,
+    InvalidDisplaySize,
+}
+
+impl RejectReason {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RejectReason::PostOnlyWouldTake => "POST_ONLY_WOULD_TAKE",
+            RejectReason::NotAStopOrder => "NOT_A_STOP_ORDER",
+            RejectReason::PriceNotOnTick => "PRICE_NOT_ON_TICK",
+            RejectReason::PricePrecisionExceeded => "PRICE_PRECISION_EXCEEDED",
+            RejectReason::SizeNotOnLot => "SIZE_NOT_ON_LOT",
+            RejectReason::SizeBelowMinimum => "SIZE_BELOW_MINIMUM",
+            RejectReason::SizeAboveMaximum => "SIZE_ABOVE_MAXIMUM",
+            RejectReason::NotionalBelowMinimum => "NOTIONAL_BELOW_MINIMUM",
+            RejectReason::InvalidDisplaySize => "INVALID_DISPLAY_SIZE",
+        }
+    }
+
+    const ALL: [RejectReason; 9] = [
+        RejectReason::PostOnlyWouldTake,
+        RejectReason::NotAStopOrder,
+        RejectReason::PriceNotOnTick,
+        RejectReason::PricePrecisionExceeded,
+        RejectReason::SizeNotOnLot,
+        RejectReason::SizeBelowMinimum,
+        RejectReason::SizeAboveMaximum,
+        RejectReason::NotionalBelowMinimum,
+        RejectReason::InvalidDisplaySize,
+    ];
+
+    fn encode(&self, writer: &mut Writer) {
+        writer.u8(*self as u8);
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, StorageError> {
+        let tag = reader.u8()?;
+        Self::ALL
+            .get(tag as usize)
+            .copied()
+            .ok_or_else(|| StorageError::Corrupt(format!("unknown reject reason {}", tag)))
+    }
+}
+
+// Derived structure for RejectLog
+#[derive(Debug)]
+pub(crate) struct RejectLog {
+    base: Base,
+    pub(crate) order_id: String,
+    size: Decimal,
+    price: Decimal,
+    pub(crate) reason: RejectReason,
+    bid_or_ask: BidOrAsk,
+}
+
+impl RejectLog {
+    pub(crate) fn new(sequence: i64, order_id: String, size: Decimal, price: Decimal, reason: RejectReason, bid_or_ask: BidOrAsk) -> Self {
+        RejectLog {
+            base: Base::new(LogType::Reject, sequence, SystemTime::now()),
+            order_id,
+            size,
+            price,
+            reason,
+            bid_or_ask,
+        }
+    }
+}
+
+impl Log for RejectLog {
+    fn get_seq(&self) -> i64 {
+        self.base.sequence
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        self.base.encode(writer);
+        writer.string(&self.order_id);
+        writer.decimal(self.size);
+        writer.decimal(self.price);
+        self.reason.encode(writer);
+        self.bid_or_ask.encode(writer);
+    }
+}
+
+// Derived structure for TriggerLog
+#[derive(Debug)]
+pub(crate) struct TriggerLog {
+    base: Base,
+    pub(crate) order_id: String,
+    stop_price: Decimal,
+    last_price: Decimal,
+    bid_or_ask: BidOrAsk,
+}
+
+impl TriggerLog {
+    pub(crate) fn new(sequence: i64, order_id: String, stop_price: Decimal, last_price: Decimal, bid_or_ask: BidOrAsk) -> Self {
+        TriggerLog {
+            base: Base::new(LogType::Trigger, sequence, SystemTime::now()),
+            order_id,
+            stop_price,
+            last_price,
+            bid_or_ask,
+        }
+    }
+}
+
+impl Log for TriggerLog {
+    fn get_seq(&self) -> i64 {
+        self.base.sequence
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        self.base.encode(writer);
+        writer.string(&self.order_id);
+        writer.decimal(self.stop_price);
+        writer.decimal(self.last_price);
+        self.bid_or_ask.encode(writer);
+    }
+}
+
+// Derived structure for AmendLog
+#[derive(Debug)]
+pub(crate) struct AmendLog {
+    base: Base,
+    pub(crate) order_id: String,
+    pub(crate) old_price: Decimal,
+    pub(crate) old_size: Decimal,
+    pub(crate) price: Decimal,
+    pub(crate) size: Decimal,
+    pub(crate) bid_or_ask: BidOrAsk,
+    pub(crate) keeps_priority: bool,
+}
+
+impl AmendLog {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(sequence: i64, order_id: String, old_price: Decimal, old_size: Decimal, price: Decimal, size: Decimal, bid_or_ask: BidOrAsk, keeps_priority: bool) -> Self {
+        AmendLog {
+            base: Base::new(LogType::Amend, sequence, SystemTime::now()),
+            order_id,
+            old_price,
+            old_size,
+            price,
+            size,
+            bid_or_ask,
+            keeps_priority,
+        }
+    }
+}
+
+impl Log for AmendLog {
+    fn get_seq(&self) -> i64 {
+        self.base.sequence
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        self.base.encode(writer);
+        writer.string(&self.order_id);
+        writer.decimal(self.old_price);
+        writer.decimal(self.old_size);
+        writer.decimal(self.price);
+        writer.decimal(self.size);
+        self.bid_or_ask.encode(writer);
+        writer.bool(self.keeps_priority);
+    }
+}
+
@@ -1,7 +1,20 @@
+mod candles;
+mod codec;
+mod depth;
+mod engine;
+mod error;
+mod exchange;
+mod instrument;
+mod journal;
+mod l2_feed;
+mod l3_feed;
 mod limit;
 mod log;
 mod match_result;
 mod order;
 mod tests;
 mod order_book;
+mod recovery;
 mod snapshot;
+mod snapshot_store;
+mod trigger_book;
@@ -1,20 +1,127 @@
 #![allow(dead_code)]
+use crate::core::codec::{Reader, Writer};
+use crate::core::error::StorageError;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use std::time::SystemTime;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BidOrAsk {
     Bid,
     Ask,
 }
 
-#[derive(Debug, Clone)]
+impl BidOrAsk {
+    /// Writes the side as a single byte.
+    pub(crate) fn encode(&self, writer: &mut Writer) {
+        writer.u8(match self {
+            BidOrAsk::Bid => 0,
+            BidOrAsk::Ask => 1,
+        });
+    }
+
+    /// Reads a side written by `encode`.
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self, StorageError> {
+        match reader.u8()? {
+            0 => Ok(BidOrAsk::Bid),
+            1 => Ok(BidOrAsk::Ask),
+            tag => Err(StorageError::Corrupt(format!("unknown side {}", tag))),
+        }
+    }
+}
+
+/// How long an order remains working before it is canceled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeInForce {
+    /// Rests in the book until it is filled or canceled.
+    GoodTillCancel,
+    /// Matches what it can immediately and cancels the remainder.
+    ImmediateOrCancel,
+    /// Fills completely and immediately, or is rejected without trading.
+    FillOrKill,
+    /// Rests in the book until it is filled, canceled or the given time passes.
+    GoodTillDate(SystemTime),
+}
+
+/// How an order is executed once it reaches the book.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderType {
+    /// Trades at the given price or better, resting any remainder.
+    Limit,
+    /// Trades immediately against the best available prices.
+    Market,
+    /// Becomes a market order once the last trade price reaches the stop price.
+    StopMarket(Decimal),
+    /// Becomes a limit order once the last trade price reaches the stop price.
+    StopLimit(Decimal),
+}
+
+impl OrderType {
+    /// Writes the order type as a byte, followed by the stop price of a stop order.
+    pub(crate) fn encode(&self, writer: &mut Writer) {
+        match self {
+            OrderType::Limit => writer.u8(0),
+            OrderType::Market => writer.u8(1),
+            OrderType::StopMarket(stop_price) => {
+                writer.u8(2);
+                writer.decimal(*stop_price);
+            }
+            OrderType::StopLimit(stop_price) => {
+                writer.u8(3);
+                writer.decimal(*stop_price);
+            }
+        }
+    }
+
+    /// Reads an order type written by `encode`.
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self, StorageError> {
+        match reader.u8()? {
+            0 => Ok(OrderType::Limit),
+            1 => Ok(OrderType::Market),
+            2 => Ok(OrderType::StopMarket(reader.decimal()?)),
+            3 => Ok(OrderType::StopLimit(reader.decimal()?)),
+            tag => Err(StorageError::Corrupt(format!("unknown order type {}", tag))),
+        }
+    }
+}
+
+/// What to do with a post-only order that would take liquidity on arrival.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostOnly {
+    /// Reject the order without trading.
+    Reject,
+    /// Reprice the order one tick away from the best opposing price so that it rests.
+    Slide,
+}
+
+/// How the book resolves an incoming order that would trade against a resting order of the
+/// same owner.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelfTradePrevention {
+    /// Cancel the remainder of the incoming order.
+    CancelNewest,
+    /// Cancel the resting order and keep matching the incoming order.
+    CancelOldest,
+    /// Cancel both the incoming and the resting order.
+    CancelBoth,
+    /// Reduce both orders by the smaller of their sizes and cancel whichever reaches zero. A
+    /// resting order that keeps some size is reported with an `AmendLog` and keeps its priority.
+    DecrementAndCancel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Order {
     pub(crate) id: String,
     pub(crate) price: Decimal,
     pub size: Decimal,
+    pub(crate) display_size: Option<Decimal>, // The size shown in the book at a time, for iceberg orders.
+    pub(crate) hidden_size: Decimal,          // The reserve of a resting iceberg order not shown in the book.
     pub(crate) bid_or_ask: BidOrAsk,
+    pub(crate) owner: Option<String>, // The account that owns the order, for self-trade prevention.
+    pub(crate) order_type: OrderType,
+    pub(crate) time_in_force: TimeInForce,
+    pub(crate) post_only: Option<PostOnly>,
+    pub(crate) position: u64, // Time-priority ticket assigned by the `Limit` the order rests in.
     created_at: SystemTime,
 }
 
@@ -25,11 +132,109 @@ impl Order {
             bid_or_ask,
             price,
             size,
+            display_size: None,
+            hidden_size: dec!(0),
+            owner: None,
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::GoodTillCancel,
+            post_only: None,
+            position: 0,
             created_at: SystemTime::now(),
         }
     }
 
+    pub fn with_owner(mut self, owner: String) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    pub fn with_post_only(mut self, post_only: PostOnly) -> Self {
+        self.post_only = Some(post_only);
+        self
+    }
+
+    pub fn with_display_size(mut self, display_size: Decimal) -> Self {
+        self.display_size = Some(display_size);
+        self
+    }
+
+    pub fn total_size(&self) -> Decimal {
+        self.size + self.hidden_size
+    }
+
     pub fn is_filled(&self) -> bool {
         self.size == dec!(0)
     }
+
+    pub fn stop_price(&self) -> Option<Decimal> {
+        match self.order_type {
+            OrderType::StopMarket(stop_price) | OrderType::StopLimit(stop_price) => Some(stop_price),
+            OrderType::Limit | OrderType::Market => None,
+        }
+    }
+
+    /// Writes the order in the engine's binary format.
+    pub(crate) fn encode(&self, writer: &mut Writer) {
+        writer.string(&self.id);
+        writer.decimal(self.price);
+        writer.decimal(self.size);
+        writer.option(self.display_size, Writer::decimal);
+        writer.decimal(self.hidden_size);
+        self.bid_or_ask.encode(writer);
+        writer.option(self.owner.as_deref(), Writer::string);
+        self.order_type.encode(writer);
+        match self.time_in_force {
+            TimeInForce::GoodTillCancel => writer.u8(0),
+            TimeInForce::ImmediateOrCancel => writer.u8(1),
+            TimeInForce::FillOrKill => writer.u8(2),
+            TimeInForce::GoodTillDate(expire_at) => {
+                writer.u8(3);
+                writer.time(expire_at);
+            }
+        }
+        writer.option(self.post_only.as_ref(), |writer, post_only| match post_only {
+            PostOnly::Reject => writer.u8(0),
+            PostOnly::Slide => writer.u8(1),
+        });
+        writer.u64(self.position);
+        writer.time(self.created_at);
+    }
+
+    /// Reads an order written by `encode`.
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self, StorageError> {
+        Ok(Order {
+            id: reader.string()?,
+            price: reader.decimal()?,
+            size: reader.decimal()?,
+            display_size: reader.option(Reader::decimal)?,
+            hidden_size: reader.decimal()?,
+            bid_or_ask: BidOrAsk::decode(reader)?,
+            owner: reader.option(Reader::string)?,
+            order_type: OrderType::decode(reader)?,
+            time_in_force: match reader.u8()? {
+                0 => TimeInForce::GoodTillCancel,
+                1 => TimeInForce::ImmediateOrCancel,
+                2 => TimeInForce::FillOrKill,
+                3 => TimeInForce::GoodTillDate(reader.time()?),
+                tag => return Err(StorageError::Corrupt(format!("unknown time in force {}", tag))),
+            },
+            post_only: reader.option(|reader| match reader.u8()? {
+                0 => Ok(PostOnly::Reject),
+                1 => Ok(PostOnly::Slide),
+                tag => Err(StorageError::Corrupt(format!("unknown post-only mode {}", tag))),
+            })?,
+            position: reader.u64()?,
+            created_at: reader.time()?,
+        })
+    }
 }
@@ -1,26 +1,161 @@
+#![allow(dead_code)]
+use crate::core::depth::{Depth, PriceLevel};
+use crate::core::error::MatchingError;
 use crate::core::limit::Limit;
-use crate::core::log::{Log, OpenLog};
-use crate::core::order::{BidOrAsk, Order};
+use crate::core::instrument::InstrumentSpec;
+use crate::core::log::{DoneLog, Log, OpenLog, ReceivedLog, RejectLog, RejectReason, TriggerLog};
+use crate::core::order::{BidOrAsk, Order, OrderType, PostOnly, SelfTradePrevention, TimeInForce};
 use crate::core::snapshot::{Snapshot, SnapshotData};
+use crate::core::trigger_book::TriggerBook;
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use rust_decimal_macros::dec;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::SystemTime;
 
 /// Represents an order book containing bid and ask limits.
 /// The `OrderBook` struct manages buy and sell orders, organized by price levels.
+///
+/// Both sides are kept in price-ordered maps, so the top of book is always at one end of the
+/// map and matching walks the levels in place instead of sorting them on every call.
 #[derive(Debug)]
 pub struct OrderBook {
-    pub(crate) asks: HashMap<Decimal, Limit>, // Map of price levels to ask (sell) limits.
-    pub(crate) bids: HashMap<Decimal, Limit>, // Map of price levels to bid (buy) limits.
-    sequence: i64,                            // Add sequence counter
+    pub(crate) asks: BTreeMap<Decimal, Limit>,          // Ask (sell) limits, best (lowest) price first.
+    pub(crate) bids: BTreeMap<Decimal, Limit>,          // Bid (buy) limits, best (highest) price last.
+    pub(crate) index: HashMap<String, OrderLocation>,   // Where each resting order lives, by ID.
+    expiries: BTreeSet<(SystemTime, String)>,           // Good-till-date orders, earliest expiry first.
+    triggers: TriggerBook,                              // Stop orders waiting for their stop price.
+    last_trade_price: Option<Decimal>,                  // The price of the most recent trade.
+    spec: InstrumentSpec,                               // The trading rules orders must conform to.
+    self_trade_prevention: Option<SelfTradePrevention>, // How to resolve orders of the same owner meeting.
+    halted: bool,                                       // Whether new orders and amendments are refused.
+    sequence: i64,                                      // The sequence of the last log.
+    trade_sequence: i64,                                // The ID of the last trade.
+}
+
+/// The location of a resting order inside the book.
+#[derive(Debug, Clone)]
+pub(crate) struct OrderLocation {
+    pub(crate) bid_or_ask: BidOrAsk, // The side of the book holding the order.
+    pub(crate) price: Decimal,       // The price of the limit holding the order.
+    pub(crate) position: u64,        // The time-priority position of the order within its limit.
 }
 
 impl OrderBook {
     /// Creates a new, empty order book with no bid or ask limits.
     pub fn new() -> OrderBook {
+        OrderBook::with_spec(InstrumentSpec::default())
+    }
+
+    /// Creates a new, empty order book that validates orders against the given instrument
+    /// specification.
+    pub fn with_spec(spec: InstrumentSpec) -> OrderBook {
         OrderBook {
-            asks: HashMap::new(),
-            bids: HashMap::new(),
-            sequence: 0, // Initialize sequence counter
+            asks: BTreeMap::new(),
+            bids: BTreeMap::new(),
+            index: HashMap::new(),
+            expiries: BTreeSet::new(),
+            triggers: TriggerBook::new(),
+            last_trade_price: None,
+            spec,
+            self_trade_prevention: None,
+            halted: false,
+            sequence: 0,
+            trade_sequence: 0,
+        }
+    }
+
+    /// Returns the instrument specification of the book.
+    pub fn spec(&self) -> &InstrumentSpec {
+        &self.spec
+    }
+
+    /// Returns how the book resolves an incoming order meeting a resting order of the same owner,
+    /// or `None` if self trades are allowed.
+    pub fn self_trade_prevention(&self) -> Option<&SelfTradePrevention> {
+        self.self_trade_prevention.as_ref()
+    }
+
+    /// Sets how the book resolves an incoming order meeting a resting order of the same owner.
+    ///
+    /// # Arguments
+    /// * `self_trade_prevention` - The mode to apply, or `None` to allow self trades.
+    pub(crate) fn set_self_trade_prevention(&mut self, self_trade_prevention: Option<SelfTradePrevention>) {
+        self.self_trade_prevention = self_trade_prevention;
+    }
+
+    /// Halts the book. While halted, new orders and amendments are refused with
+    /// `MatchingError::BookHalted`; cancels and expiries are still processed.
+    pub(crate) fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    /// Resumes a halted book.
+    pub(crate) fn resume(&mut self) {
+        self.halted = false;
+    }
+
+    /// Acknowledges an order entering the book, before it is checked or matched.
+    ///
+    /// # Returns
+    /// * A `ReceivedLog` for the order, sequenced ahead of every log its placement produces.
+    pub(crate) fn receive_order(&mut self, order: &Order) -> ReceivedLog {
+        ReceivedLog::new(
+            self.next_log_seq(),
+            order.id.clone(),
+            order.bid_or_ask.clone(),
+            order.order_type.clone(),
+            order.size,
+            order.price,
+        )
+    }
+
+    /// Returns `true` if the book is halted.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns the sequence of the last log produced by the book.
+    pub fn log_seq(&self) -> i64 {
+        self.sequence
+    }
+
+    /// Returns the ID of the last trade executed on the book.
+    pub fn trade_seq(&self) -> i64 {
+        self.trade_sequence
+    }
+
+    /// Checks that a new order can be accepted by the book.
+    ///
+    /// # Arguments
+    /// * `order` - The incoming order.
+    /// * `limit_price` - The price the order rests or trades at, or `None` for a market order.
+    ///
+    /// # Returns
+    /// * `Ok(())`, or the `MatchingError` describing why the order is refused.
+    fn check_new_order(&self, order: &Order, limit_price: Option<Decimal>) -> Result<(), MatchingError> {
+        if self.halted {
+            return Err(MatchingError::BookHalted);
+        }
+        if self.index.contains_key(&order.id) || self.triggers.get(&order.id).is_some() {
+            return Err(MatchingError::DuplicateOrderId(order.id.clone()));
+        }
+        if order.size <= dec!(0) {
+            return Err(MatchingError::InvalidSize(order.size));
+        }
+        match limit_price {
+            Some(price) if price <= dec!(0) => Err(MatchingError::InvalidPrice(price)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks an incoming order against the instrument specification.
+    ///
+    /// # Returns
+    /// * `Ok(())`, or the `RejectLog` to report for a non-conforming order.
+    fn check_spec(&mut self, order: &Order, limit_price: Option<Decimal>) -> Result<(), RejectLog> {
+        match self.spec.validate(order, limit_price) {
+            Ok(()) => Ok(()),
+            Err(reason) => Err(self.reject_log(order, reason)),
         }
     }
 
@@ -29,72 +164,425 @@ impl OrderBook {
         self.sequence
     }
 
+    fn limits_mut(&mut self, bid_or_ask: &BidOrAsk) -> &mut BTreeMap<Decimal, Limit> {
+        match bid_or_ask {
+            BidOrAsk::Bid => &mut self.bids,
+            BidOrAsk::Ask => &mut self.asks,
+        }
+    }
+
+    fn limits(&self, bid_or_ask: &BidOrAsk) -> &BTreeMap<Decimal, Limit> {
+        match bid_or_ask {
+            BidOrAsk::Bid => &self.bids,
+            BidOrAsk::Ask => &self.asks,
+        }
+    }
+
+    /// Places an order, dispatching on its `OrderType`.
+    ///
+    /// Market orders are filled against the book, limit orders go through `place_limit_order`,
+    /// and stop orders are held in the trigger book until their stop price is reached.
+    ///
+    /// # Arguments
+    /// * `order` - The `Order` to place.
+    ///
+    /// # Returns
+    /// * A `Vec<Box<dyn Log>>` containing every log produced while placing the order, or a
+    ///   `MatchingError` if the order is refused.
+    pub(crate) fn place_order(&mut self, mut order: Order) -> Result<Vec<Box<dyn Log>>, MatchingError> {
+        match order.order_type {
+            OrderType::Market => self.fill_market_order(&mut order),
+            OrderType::Limit => self.place_limit_order(order),
+            OrderType::StopMarket(_) | OrderType::StopLimit(_) => self.place_stop_order(order),
+        }
+    }
+
     /// Fills a market order by matching it with the opposing limit orders.
     ///
-    /// This function identifies the appropriate limits (asks for bid orders and bids for ask orders)
-    /// and iteratively attempts to fill the market order by matching it with the orders in these limits.
+    /// This function walks the opposing side of the book (asks for bid orders and bids for ask orders)
+    /// from the best price outwards and fills the market order against the resting orders of each
+    /// limit in place. Resting orders are reduced or removed as they are consumed, and limits that
+    /// are left without orders are removed from the book.
+    ///
+    /// A fill-or-kill market order that cannot be filled completely is rejected without trading.
+    /// If the opposing side runs out, the unfilled remainder is canceled with a `DoneLog`. Any
+    /// stop orders triggered by the resulting trades are executed before returning.
     ///
     /// # Arguments
     /// * `market_order` - A mutable reference to the market order that needs to be filled.
     ///
     /// # Returns
-    /// * A `Vec<Box<dyn Log>>` containing logs for matches and open orders.
-    pub fn fill_market_order(&mut self, market_order: &mut Order) -> Vec<Box<dyn Log>> {
-        let mut logs: Vec<Box<dyn Log>> = vec![];
-        let mut limits = match market_order.bid_or_ask {
-            BidOrAsk::Bid => self.ask_limits(), // Bids consume asks.
-            BidOrAsk::Ask => self.bid_limits(), // Asks consume bids.
+    /// * A `Vec<Box<dyn Log>>` containing logs for matches and filled orders, or a
+    ///   `MatchingError` if the order is refused.
+    pub(crate) fn fill_market_order(&mut self, market_order: &mut Order) -> Result<Vec<Box<dyn Log>>, MatchingError> {
+        self.check_new_order(market_order, None)?;
+        if let Err(log) = self.check_spec(market_order, None) {
+            return Ok(vec![Box::new(log)]);
+        }
+
+        let mut logs = self.execute_market_order(market_order);
+        self.process_triggers(&mut logs);
+        Ok(logs)
+    }
+
+    /// Places a limit order, matching it against the opposing side before resting it.
+    ///
+    /// The order first takes liquidity from every opposing limit whose price is at least as good
+    /// as its own limit price. What happens to the unfilled remainder depends on the order's
+    /// time in force:
+    /// - Good-till-cancel and good-till-date orders rest in the book at the order's price.
+    /// - Immediate-or-cancel orders have the remainder canceled.
+    /// - Fill-or-kill orders are checked against the available liquidity up front and rejected
+    ///   without trading if they cannot be filled completely.
+    ///
+    /// Post-only orders never take liquidity. If one would cross the best opposing limit, it is
+    /// either rejected with a `RejectLog` or slid to one tick away from the best opposing price,
+    /// depending on its `PostOnly` mode.
+    ///
+    /// Any stop orders triggered by the resulting trades are executed before returning.
+    ///
+    /// # Arguments
+    /// * `order` - The limit `Order` to place.
+    ///
+    /// # Returns
+    /// * A `Vec<Box<dyn Log>>` containing logs for matches and filled orders, followed by the
+    ///   `OpenLog` for a resting remainder, the `DoneLog` for a canceled or killed order, or the
+    ///   `RejectLog` for a rejected post-only order. A `MatchingError` is returned if the order is
+    ///   refused.
+    pub(crate) fn place_limit_order(&mut self, order: Order) -> Result<Vec<Box<dyn Log>>, MatchingError> {
+        self.check_new_order(&order, Some(order.price))?;
+        if let Err(log) = self.check_spec(&order, Some(order.price)) {
+            return Ok(vec![Box::new(log)]);
+        }
+
+        let mut logs = self.execute_limit_order(order);
+        self.process_triggers(&mut logs);
+        Ok(logs)
+    }
+
+    /// Places a stop or stop-limit order in the trigger book.
+    ///
+    /// If the last trade price has already reached the stop price, the order is triggered and
+    /// executed immediately.
+    ///
+    /// # Arguments
+    /// * `order` - The stop `Order` to place.
+    ///
+    /// # Returns
+    /// * A `Vec<Box<dyn Log>>` containing the logs of any orders triggered as a result, or a
+    ///   `RejectLog` if the order has no stop price. A `MatchingError` is returned if the order
+    ///   is refused.
+    pub(crate) fn place_stop_order(&mut self, order: Order) -> Result<Vec<Box<dyn Log>>, MatchingError> {
+        let Some(stop_price) = order.stop_price() else {
+            self.check_new_order(&order, None)?;
+            return Ok(vec![Box::new(self.reject_log(&order, RejectReason::NotAStopOrder))]);
         };
+        self.check_new_order(&order, Some(stop_price))?;
+        let limit_price = match order.order_type {
+            OrderType::StopLimit(_) => Some(order.price), // The limit price applies once triggered.
+            _ => None,
+        };
+        if limit_price.is_some() {
+            self.check_new_order(&order, limit_price)?;
+        }
+        if let Err(log) = self.check_spec(&order, limit_price) {
+            return Ok(vec![Box::new(log)]);
+        }
+        if let TimeInForce::GoodTillDate(expire_at) = order.time_in_force {
+            self.expiries.insert((expire_at, order.id.clone()));
+        }
 
-        // Collect indices of limits to remove
-        let mut remove_indices = Vec::new();
-        for (index, limit_order) in limits.iter_mut().enumerate() {
-            let sequence = self.next_log_seq();
-            let result = limit_order.fill_order(market_order, sequence);
+        self.triggers.add(order);
+        let mut logs = vec![];
+        self.process_triggers(&mut logs);
+        Ok(logs)
+    }
 
-            if limit_order.orders.is_empty() {
-                remove_indices.push(index);
+    /// Executes every stop order triggered by the last trade price.
+    ///
+    /// Triggered orders are executed one batch at a time in trigger-book order, each preceded by
+    /// a `TriggerLog`. Trades made by one batch may move the last trade price far enough to
+    /// trigger further stops, which are executed in the same call.
+    fn process_triggers(&mut self, logs: &mut Vec<Box<dyn Log>>) {
+        while let Some(last_price) = self.last_trade_price {
+            let triggered = self.triggers.take_triggered(last_price);
+            if triggered.is_empty() {
+                break;
             }
 
-            logs.extend(result); // Collect logs for matches and open orders.
+            for mut order in triggered {
+                let sequence = self.next_log_seq();
+                let stop_price = order.stop_price().unwrap_or(last_price);
+                logs.push(Box::new(TriggerLog::new(
+                    sequence,
+                    order.id.clone(),
+                    stop_price,
+                    last_price,
+                    order.bid_or_ask.clone(),
+                )));
+
+                if let OrderType::StopLimit(_) = order.order_type {
+                    order.order_type = OrderType::Limit;
+                    logs.extend(self.execute_limit_order(order));
+                } else {
+                    order.order_type = OrderType::Market;
+                    logs.extend(self.execute_market_order(&mut order));
+                }
+            }
+        }
+    }
+
+    fn execute_market_order(&mut self, market_order: &mut Order) -> Vec<Box<dyn Log>> {
+        if market_order.time_in_force == TimeInForce::FillOrKill && !self.can_fill(market_order, None) {
+            return vec![Box::new(self.done_log(market_order, "KILLED"))];
+        }
+
+        let mut logs = self.match_order(market_order, None);
+        if !market_order.is_filled() {
+            logs.push(Box::new(self.done_log(market_order, "CANCELED"))); // The book ran out of liquidity.
+        }
+        logs
+    }
 
-            if market_order.is_filled() {
-                break; // Stop once the market order is completely filled.
+    fn execute_limit_order(&mut self, mut order: Order) -> Vec<Box<dyn Log>> {
+        if let Some(post_only) = order.post_only.clone() {
+            if let Some(best_price) = self.crossed_price(&order) {
+                match post_only {
+                    PostOnly::Reject => {
+                        return vec![Box::new(self.reject_log(&order, RejectReason::PostOnlyWouldTake))];
+                    }
+                    PostOnly::Slide => {
+                        let price = match order.bid_or_ask {
+                            BidOrAsk::Bid => best_price - self.spec.tick_size, // Sit just below the best ask.
+                            BidOrAsk::Ask => best_price + self.spec.tick_size, // Sit just above the best bid.
+                        };
+                        if price <= dec!(0) {
+                            // There is no valid price below the best ask to slide to.
+                            return vec![Box::new(self.reject_log(&order, RejectReason::PostOnlyWouldTake))];
+                        }
+                        if let Err(log) = self.check_spec(&order, Some(price)) {
+                            return vec![Box::new(log)]; // The slid price must meet the spec too.
+                        }
+                        order.price = price;
+                    }
+                }
             }
         }
 
-        // Remove the limits after the iteration
-        for index in remove_indices.iter().rev() {
-            limits.remove(*index);
+        let limit_price = order.price;
+        if order.time_in_force == TimeInForce::FillOrKill && !self.can_fill(&order, Some(limit_price)) {
+            return vec![Box::new(self.done_log(&order, "KILLED"))];
+        }
+
+        let mut logs = self.match_order(&mut order, Some(limit_price));
+        if order.is_filled() {
+            return logs;
+        }
+
+        match order.time_in_force {
+            TimeInForce::GoodTillCancel | TimeInForce::GoodTillDate(_) => {
+                logs.push(Box::new(self.rest_order(order.price, order))); // Rest the remainder.
+            }
+            TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill => {
+                logs.push(Box::new(self.done_log(&order, "CANCELED"))); // Drop the remainder.
+            }
         }
 
         logs
     }
 
-    /// Retrieves all ask (sell) limits, sorted by the cheapest price first.
+    /// Checks whether the opposing side holds enough liquidity to fill an order completely.
     ///
-    /// This function sorts the ask limits in ascending order of price, which is required
-    /// for processing bid (buy) orders to match the lowest-priced sell orders.
+    /// Resting orders that self-trade prevention keeps from trading with the order are not
+    /// counted, so a fill-or-kill order is never left partly filled by them.
+    ///
+    /// # Arguments
+    /// * `taker` - The incoming order.
+    /// * `limit_price` - The worst price the order may trade at, or `None` for a market order.
+    fn can_fill(&self, taker: &Order, limit_price: Option<Decimal>) -> bool {
+        let limits: Box<dyn Iterator<Item = &Limit>> = match taker.bid_or_ask {
+            BidOrAsk::Bid => Box::new(self.ask_limits()),
+            BidOrAsk::Ask => Box::new(self.bid_limits()),
+        };
+
+        let mut available = Decimal::ZERO;
+        for limit in limits.take_while(|limit| crosses(&taker.bid_or_ask, limit_price, limit.price)) {
+            let (volume, stops) = limit.tradable_volume(taker.owner.as_ref(), self.self_trade_prevention.as_ref());
+            available += volume; // Hidden reserves can be traded too.
+            if available >= taker.size || stops {
+                return available >= taker.size;
+            }
+        }
+        false
+    }
+
+    /// Returns the price of the best opposing limit if the order would trade against it.
+    fn crossed_price(&self, order: &Order) -> Option<Decimal> {
+        let best = match order.bid_or_ask {
+            BidOrAsk::Bid => self.best_ask(),
+            BidOrAsk::Ask => self.best_bid(),
+        }?;
+        crosses(&order.bid_or_ask, Some(order.price), best.price).then_some(best.price)
+    }
+
+    fn reject_log(&mut self, order: &Order, reason: RejectReason) -> RejectLog {
+        let sequence = self.next_log_seq();
+        RejectLog::new(
+            sequence,
+            order.id.clone(),
+            order.size,
+            order.price,
+            reason,
+            order.bid_or_ask.clone(),
+        )
+    }
+
+    fn done_log(&mut self, order: &Order, reason: &str) -> DoneLog {
+        let sequence = self.next_log_seq();
+        DoneLog::new(
+            sequence,
+            order.id.clone(),
+            order.price,
+            order.size,
+            reason.to_string(),
+            order.bid_or_ask.clone(),
+        )
+    }
+
+    /// Matches an incoming order against the opposing side of the book.
+    ///
+    /// Limits are consumed from the best price outwards until the order is filled, the opposing
+    /// side is exhausted, or the next limit is priced beyond `limit_price`.
+    ///
+    /// # Arguments
+    /// * `taker` - The incoming order, reduced in place as it is filled.
+    /// * `limit_price` - The worst price the order may trade at, or `None` for a market order.
     ///
     /// # Returns
-    /// * A vector of mutable references to `Limit` sorted by price.
-    pub fn ask_limits(&self) -> Vec<Limit> {
-        let mut limits = self.asks.values().cloned().collect::<Vec<Limit>>();
-        limits.sort_by(|a, b| a.price.cmp(&b.price));
-        limits
+    /// * A `Vec<Box<dyn Log>>` containing logs for matches and filled orders.
+    fn match_order(&mut self, taker: &mut Order, limit_price: Option<Decimal>) -> Vec<Box<dyn Log>> {
+        let mut logs: Vec<Box<dyn Log>> = vec![];
+
+        while !taker.is_filled() {
+            let entry = match taker.bid_or_ask {
+                BidOrAsk::Bid => self.asks.first_entry(), // Bids consume the cheapest asks.
+                BidOrAsk::Ask => self.bids.last_entry(),  // Asks consume the highest bids.
+            };
+            let Some(mut entry) = entry else {
+                break; // The opposing side of the book is exhausted.
+            };
+            if !crosses(&taker.bid_or_ask, limit_price, *entry.key()) {
+                break; // The best opposing limit is priced beyond the order's limit.
+            }
+
+            let price = *entry.key();
+            let result = entry
+                .get_mut()
+                .fill_order(
+                    taker,
+                    self.self_trade_prevention.as_ref(),
+                    &mut self.sequence,
+                    &mut self.trade_sequence,
+                );
+            if result.traded {
+                self.last_trade_price = Some(price); // Every fill at a limit trades at its price.
+            }
+            logs.extend(result.logs); // Collect logs for matches and filled orders.
+            for id in result.filled {
+                self.index.remove(&id); // Filled orders are no longer resting.
+            }
+            for (id, position) in result.requeued {
+                if let Some(location) = self.index.get_mut(&id) {
+                    location.position = position; // Refreshed icebergs lose their time priority.
+                }
+            }
+
+            if entry.get().orders.is_empty() {
+                entry.remove(); // Drop price levels that have been fully consumed.
+            } else {
+                break; // The level still has resting orders, so the taker is done.
+            }
+        }
+
+        logs
+    }
+
+    /// Returns the best (lowest priced) ask limit, if any.
+    pub fn best_ask(&self) -> Option<&Limit> {
+        self.asks.values().next()
+    }
+
+    /// Returns the best (highest priced) bid limit, if any.
+    pub fn best_bid(&self) -> Option<&Limit> {
+        self.bids.values().next_back()
+    }
+
+    /// Returns the lowest ask price, if any ask is resting.
+    pub fn best_ask_price(&self) -> Option<Decimal> {
+        self.levels(&BidOrAsk::Ask).next().map(|level| level.price)
+    }
+
+    /// Returns the highest bid price, if any bid is resting.
+    pub fn best_bid_price(&self) -> Option<Decimal> {
+        self.levels(&BidOrAsk::Bid).next().map(|level| level.price)
+    }
+
+    /// Returns the best ask price minus the best bid price, if both sides have orders.
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask_price()? - self.best_bid_price()?)
+    }
+
+    /// Returns the price halfway between the best bid and the best ask, if both sides have orders.
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.best_ask_price()? + self.best_bid_price()?) / dec!(2))
+    }
+
+    /// Aggregates the top of the book into price levels.
+    ///
+    /// # Arguments
+    /// * `n` - The maximum number of levels to return per side.
+    ///
+    /// # Returns
+    /// * A `Depth` holding up to `n` levels per side, best price first. Levels without orders are
+    ///   skipped.
+    pub fn depth(&self, n: usize) -> Depth {
+        Depth {
+            bids: self.levels(&BidOrAsk::Bid).take(n).collect(),
+            asks: self.levels(&BidOrAsk::Ask).take(n).collect(),
+        }
+    }
+
+    /// Iterates over the non-empty price levels of one side, best price first.
+    fn levels(&self, bid_or_ask: &BidOrAsk) -> impl Iterator<Item = PriceLevel> + '_ {
+        let limits: Box<dyn Iterator<Item = &Limit>> = match bid_or_ask {
+            BidOrAsk::Bid => Box::new(self.bid_limits()),
+            BidOrAsk::Ask => Box::new(self.ask_limits()),
+        };
+        limits.filter(|limit| !limit.orders.is_empty()).map(|limit| PriceLevel {
+            price: limit.price,
+            size: limit.total_volume(),
+            order_count: limit.orders.len(),
+        })
+    }
+
+    /// Iterates over all ask (sell) limits, cheapest price first.
+    ///
+    /// This is the order in which bid (buy) orders consume the ask side of the book.
+    ///
+    /// # Returns
+    /// * An iterator over references to `Limit` sorted by ascending price.
+    pub fn ask_limits(&self) -> impl DoubleEndedIterator<Item = &Limit> {
+        self.asks.values()
     }
 
-    /// Retrieves all bid (buy) limits, sorted by the highest price first.
+    /// Iterates over all bid (buy) limits, highest price first.
     ///
-    /// This function sorts the bid limits in descending order of price, which is required
-    /// for processing ask (sell) orders to match the highest-priced buy orders.
+    /// This is the order in which ask (sell) orders consume the bid side of the book.
     ///
     /// # Returns
-    /// * A vector of mutable references to `Limit` sorted by price.
-    pub fn bid_limits(&self) -> Vec<Limit> {
-        let mut limits = self.bids.values().cloned().collect::<Vec<Limit>>();
-        limits.sort_by(|a, b| b.price.cmp(&a.price));
-        limits
+    /// * An iterator over references to `Limit` sorted by descending price.
+    pub fn bid_limits(&self) -> impl DoubleEndedIterator<Item = &Limit> {
+        self.bids.values().rev()
     }
 
     /// Adds a new limit order to the order book.
@@ -108,44 +596,335 @@ impl OrderBook {
     /// * `order` - The `Order` to be added to the limit.
     ///
     /// # Returns
-    /// * An `OpenLog` containing information about the added limit order.
-    pub fn add_limit_order(&mut self, price: Decimal, order: Order) -> OpenLog {
+    /// * An `OpenLog` containing information about the added limit order, or a `MatchingError` if
+    ///   the order is refused or does not conform to the instrument specification.
+    pub(crate) fn add_limit_order(&mut self, price: Decimal, order: Order) -> Result<OpenLog, MatchingError> {
+        self.check_new_order(&order, Some(price))?;
+        self.spec
+            .validate(&order, Some(price))
+            .map_err(MatchingError::Rejected)?;
+
+        Ok(self.rest_order(price, order))
+    }
+
+    /// Rests an order at the given price without matching it, creating the limit if needed.
+    fn rest_order(&mut self, price: Decimal, mut order: Order) -> OpenLog {
         let sequence = self.next_log_seq();
-        match order.bid_or_ask {
-            BidOrAsk::Bid => match self.bids.get_mut(&price) {
-                Some(limit) => {
-                    limit.add_order(order, sequence) // Append order to existing bid limit.
-                }
-                None => {
-                    let mut limit = Limit::new(price);
-                    let log = limit.add_order(order, sequence); // Create a new bid limit.
-                    self.bids.insert(price, limit);
-                    log
-                }
+        order.price = price; // A resting order always carries the price of its limit.
+        let id = order.id.clone();
+        let bid_or_ask = order.bid_or_ask.clone();
+        if let TimeInForce::GoodTillDate(expire_at) = order.time_in_force {
+            self.expiries.insert((expire_at, id.clone()));
+        }
+        let limit = self
+            .limits_mut(&bid_or_ask)
+            .entry(price)
+            .or_insert_with(|| Limit::new(price)); // Create the limit if it does not exist yet.
+        let log = limit.add_order(order, sequence);
+        let position = limit.orders.last().map_or(0, |order| order.position);
+
+        self.index.insert(
+            id,
+            OrderLocation {
+                bid_or_ask,
+                price,
+                position,
             },
-            BidOrAsk::Ask => match self.asks.get_mut(&price) {
-                Some(limit) => {
-                    limit.add_order(order, sequence) // Append order to existing ask limit.
-                }
-                None => {
-                    let mut limit = Limit::new(price);
-                    let log = limit.add_order(order, sequence); // Create a new ask limit.
-                    self.asks.insert(price, limit);
-                    log
+        );
+        log
+    }
+
+    /// Looks up a resting order by its ID.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the order.
+    ///
+    /// # Returns
+    /// * The resting order or pending stop order, or `None` if it was filled, canceled or never
+    ///   added.
+    pub fn get_order(&self, id: &str) -> Option<&Order> {
+        let Some(location) = self.index.get(id) else {
+            return self.triggers.get(id); // The order may be a stop that has not triggered yet.
+        };
+        self.limits(&location.bid_or_ask)
+            .get(&location.price)?
+            .find_order(location.position)
+    }
+
+    /// Cancels a resting order or pending stop order by its ID.
+    ///
+    /// A resting order is removed from its limit, and the limit is removed from the book if it is
+    /// left without orders.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the order to cancel.
+    ///
+    /// # Returns
+    /// * A `DoneLog` with the reason `CANCELED`, or `MatchingError::UnknownOrder` if no resting
+    ///   order has the given ID.
+    pub(crate) fn cancel_order(&mut self, id: &str) -> Result<DoneLog, MatchingError> {
+        self.remove_order(id, "CANCELED")
+    }
+
+    /// Cancels every resting order and pending stop order matching the given filters.
+    ///
+    /// # Arguments
+    /// * `owner` - Only cancel orders of this owner, or `None` for any owner.
+    /// * `bid_or_ask` - Only cancel orders on this side, or `None` for both sides.
+    ///
+    /// # Returns
+    /// * A `Vec<Box<dyn Log>>` containing a `DoneLog` with the reason `CANCELED` for each removed
+    ///   order: asks from the best price outwards, then bids, then pending stops, each limit in
+    ///   queue order.
+    pub(crate) fn mass_cancel(&mut self, owner: Option<&str>, bid_or_ask: Option<&BidOrAsk>) -> Vec<Box<dyn Log>> {
+        let ids: Vec<String> = self
+            .asks
+            .values()
+            .chain(self.bids.values().rev())
+            .flat_map(|limit| limit.orders.iter())
+            .chain(self.triggers.orders())
+            .filter(|order| owner.is_none_or(|owner| order.owner.as_deref() == Some(owner)))
+            .filter(|order| bid_or_ask.is_none_or(|side| &order.bid_or_ask == side))
+            .map(|order| order.id.clone())
+            .collect();
+
+        let mut logs: Vec<Box<dyn Log>> = vec![];
+        for id in ids {
+            if let Ok(log) = self.remove_order(&id, "CANCELED") {
+                logs.push(Box::new(log));
+            }
+        }
+        logs
+    }
+
+    /// Removes every good-till-date order whose expiry is at or before `now`.
+    ///
+    /// # Arguments
+    /// * `now` - The current time.
+    ///
+    /// # Returns
+    /// * A `Vec<Box<dyn Log>>` containing a `DoneLog` with the reason `EXPIRED` for each removed
+    ///   order, earliest expiry first.
+    pub(crate) fn expire_orders(&mut self, now: SystemTime) -> Vec<Box<dyn Log>> {
+        let mut logs: Vec<Box<dyn Log>> = vec![];
+
+        while let Some((expire_at, id)) = self.expiries.first().cloned() {
+            if expire_at > now {
+                break;
+            }
+            self.expiries.pop_first();
+
+            // Orders that were filled or canceled since they were added leave stale entries behind.
+            let resting = self.get_order(&id).map(|order| &order.time_in_force);
+            if resting == Some(&TimeInForce::GoodTillDate(expire_at)) {
+                if let Ok(log) = self.remove_order(&id, "EXPIRED") {
+                    logs.push(Box::new(log));
                 }
+            }
+        }
+
+        logs
+    }
+
+    /// Amends the price and size of a resting order.
+    ///
+    /// A pure size reduction at the same price keeps the order's time priority. Any other change
+    /// (a new price or a larger size) moves the order to the back of the queue at its new price.
+    /// Both cases are reported with an `AmendLog`.
+    ///
+    /// If the new price would cross the opposing side, the amendment is treated as a cancel and
+    /// replace: the order is closed with a `DoneLog` with the reason `REPLACED` and placed again
+    /// under the same ID, producing the usual matching logs.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the resting order to amend.
+    /// * `new_price` - The new limit price.
+    /// * `new_size` - The new total remaining size, including any hidden iceberg reserve.
+    ///
+    /// An amendment that does not conform to the instrument specification is rejected with a
+    /// `RejectLog` and leaves the order unchanged.
+    ///
+    /// # Returns
+    /// * The logs produced by the amendment, or a `MatchingError` if the book is halted, the order
+    ///   is not resting or the new price or size is not positive.
+    pub(crate) fn amend_order(&mut self, id: &str, new_price: Decimal, new_size: Decimal) -> Result<Vec<Box<dyn Log>>, MatchingError> {
+        if self.halted {
+            return Err(MatchingError::BookHalted);
+        }
+        if new_price <= dec!(0) {
+            return Err(MatchingError::InvalidPrice(new_price));
+        }
+        if new_size <= dec!(0) {
+            return Err(MatchingError::InvalidSize(new_size));
+        }
+        let location = self
+            .index
+            .get(id)
+            .cloned()
+            .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+        let mut amended = self
+            .get_order(id)
+            .cloned()
+            .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+        let total_size = amended.total_size();
+        amended.price = new_price;
+        amended.size = new_size;
+        amended.hidden_size = dec!(0);
+        if let Err(log) = self.check_spec(&amended, Some(new_price)) {
+            return Ok(vec![Box::new(log)]); // The order is left as it was.
+        }
+
+        let sequence = self.next_log_seq();
+        let limits = self.limits_mut(&location.bid_or_ask);
+        let limit = limits
+            .get_mut(&location.price)
+            .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+
+        if new_price == location.price && new_size <= total_size {
+            let log = limit
+                .reduce_order(location.position, new_size, sequence)
+                .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+            return Ok(vec![Box::new(log)]);
+        }
+
+        let mut order = limit
+            .take_order(location.position)
+            .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+        if limit.orders.is_empty() {
+            limits.remove(&location.price); // Drop the limit once its last order has moved.
+        }
+        self.index.remove(id);
+        let old_size = order.size;
+        order.price = new_price;
+        order.size = new_size;
+        order.hidden_size = dec!(0);
+
+        if self.crossed_price(&order).is_some() {
+            let mut logs: Vec<Box<dyn Log>> = vec![Box::new(DoneLog::new(
+                sequence,
+                order.id.clone(),
+                location.price,
+                total_size,
+                "REPLACED".to_string(),
+                order.bid_or_ask.clone(),
+            ))];
+            logs.extend(self.execute_limit_order(order));
+            self.process_triggers(&mut logs);
+            return Ok(logs);
+        }
+
+        let limit = self
+            .limits_mut(&location.bid_or_ask)
+            .entry(new_price)
+            .or_insert_with(|| Limit::new(new_price));
+        let log = limit.requeue_order(order, location.price, old_size, sequence);
+        let position = limit.orders.last().map_or(0, |order| order.position);
+        self.index.insert(
+            id.to_string(),
+            OrderLocation {
+                bid_or_ask: location.bid_or_ask,
+                price: new_price,
+                position,
             },
+        );
+        Ok(vec![Box::new(log)])
+    }
+
+    fn remove_order(&mut self, id: &str, reason: &str) -> Result<DoneLog, MatchingError> {
+        if let Some(order) = self.triggers.remove(id) {
+            return Ok(self.done_log(&order, reason));
         }
+
+        let location = self
+            .index
+            .remove(id)
+            .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+        let sequence = self.next_log_seq();
+        let limits = self.limits_mut(&location.bid_or_ask);
+        let limit = limits
+            .get_mut(&location.price)
+            .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+        let log = limit
+            .cancel_order(location.position, reason, sequence)
+            .ok_or_else(|| MatchingError::UnknownOrder(id.to_string()))?;
+
+        if limit.orders.is_empty() {
+            limits.remove(&location.price); // Drop the limit once its last order is removed.
+        }
+        Ok(log)
     }
 
-    pub fn restore(&mut self, snapshot: SnapshotData) {
-        for order in snapshot.orders {
-            self.add_limit_order(order.price.clone(), order);
+    /// Restores the resting orders, pending stop orders and sequences captured by `snapshot`.
+    ///
+    /// Orders are put back exactly as they were, keeping their time priority, and no logs are
+    /// produced: the next log and the next trade continue the sequences of the book the snapshot
+    /// was taken from.
+    ///
+    /// # Returns
+    /// * `Ok(())`, or `MatchingError::DuplicateOrderId` if an order is already in the book.
+    pub(crate) fn restore(&mut self, snapshot: SnapshotData) -> Result<(), MatchingError> {
+        let (log_seq, trade_seq) = (snapshot.log_seq(), snapshot.trade_seq());
+        let last_trade_price = snapshot.last_trade_price();
+        let halted = snapshot.halted();
+        for order in snapshot.orders.iter().chain(&snapshot.stop_orders) {
+            if self.index.contains_key(&order.id) || self.triggers.get(&order.id).is_some() {
+                return Err(MatchingError::DuplicateOrderId(order.id.clone()));
+            }
         }
+
+        for order in snapshot.orders.into_iter().chain(snapshot.stop_orders) {
+            if let TimeInForce::GoodTillDate(expire_at) = order.time_in_force {
+                self.expiries.insert((expire_at, order.id.clone()));
+            }
+            if order.stop_price().is_some() {
+                self.triggers.add(order);
+                continue;
+            }
+            self.index.insert(
+                order.id.clone(),
+                OrderLocation {
+                    bid_or_ask: order.bid_or_ask.clone(),
+                    price: order.price,
+                    position: order.position,
+                },
+            );
+            self.limits_mut(&order.bid_or_ask)
+                .entry(order.price)
+                .or_insert_with(|| Limit::new(order.price))
+                .restore_order(order);
+        }
+        self.last_trade_price = last_trade_price;
+        self.halted = halted;
+        self.sequence = log_seq;
+        self.trade_sequence = trade_seq;
+        Ok(())
     }
 
-    pub fn snapshot(self, pair: String) -> SnapshotData {
+    /// Captures the resting orders and pending stop orders of the book, along with the sequence of
+    /// the last log and the ID of the last trade.
+    pub fn snapshot(&self, pair: String) -> Result<SnapshotData, MatchingError> {
         let snapshot = Snapshot::new(pair);
 
-        snapshot.construct_snapshot(self.ask_limits(), self.bid_limits(), 0, 0)
+        snapshot.construct_snapshot(
+            self.ask_limits().cloned().collect(),
+            self.bid_limits().cloned().collect(),
+            self.triggers.orders().cloned().collect(),
+            self.last_trade_price,
+            self.halted,
+            self.trade_sequence,
+            self.sequence,
+        )
+    }
+}
+
+/// Checks whether an order on the given side may trade against a limit at `level_price`.
+///
+/// Bids cross asks priced at or below their limit price, asks cross bids priced at or above it,
+/// and market orders (no limit price) cross any level.
+fn crosses(bid_or_ask: &BidOrAsk, limit_price: Option<Decimal>, level_price: Decimal) -> bool {
+    match (limit_price, bid_or_ask) {
+        (None, _) => true,
+        (Some(price), BidOrAsk::Bid) => level_price <= price,
+        (Some(price), BidOrAsk::Ask) => level_price >= price,
     }
 }
@@ -0,0 +1,114 @@
+#![allow(dead_code)]
+use crate::core::engine::{Engine, Event};
+use crate::core::error::StorageError;
+use crate::core::journal::{read_journal, JournalEntry};
+use crate::core::order_book::OrderBook;
+use crate::core::snapshot_store::SnapshotStore;
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+/// What `recover` found and did while rebuilding an engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    pub snapshot_log_seq: Option<i64>, // The log sequence of the snapshot started from, if any.
+    pub skipped_snapshots: usize,      // Newer snapshots that could not be read.
+    pub replayed_commands: usize,      // Journaled commands processed again.
+    pub replayed_logs: usize,          // Logs produced while processing them.
+    pub truncated_bytes: u64,          // The size of the torn record cut from the journal.
+}
+
+/// Rebuilds the engine of `pair` after a restart.
+///
+/// The newest readable snapshot of the pair is restored into `book`, then every journaled command
+/// issued at or after the snapshot's log sequence is processed again. Because the engine is
+/// deterministic, replay produces the same logs as the original run; the sequence of each one is
+/// checked against the journal, and every command must start at the log sequence the rebuilt book
+/// has reached. The logs of a command may be missing from the journal if a crash came before they
+/// were written; they are produced again by the replay.
+///
+/// A record torn by a crash at the end of the journal is truncated, so the journal can be opened
+/// for appending again.
+///
+/// # Arguments
+/// * `pair` - The instrument whose engine is rebuilt.
+/// * `book` - An empty book, configured like the one that was running.
+/// * `snapshots` - The store holding the snapshots of the pair.
+/// * `journal_directory` - The directory holding the journal of the pair.
+///
+/// # Returns
+/// * The rebuilt engine, without a journal attached, and a report of the recovery, or the
+///   `StorageError` describing why the state could not be rebuilt.
+pub fn recover(
+    pair: &str,
+    book: OrderBook,
+    snapshots: &SnapshotStore,
+    journal_directory: &Path,
+) -> Result<(Engine, RecoveryReport), StorageError> {
+    let (snapshot, skipped_snapshots) = snapshots.load_newest_valid(pair)?;
+    let snapshot_log_seq = snapshot.as_ref().map(|snapshot| snapshot.log_seq());
+    let mut engine = match snapshot {
+        Some(snapshot) => Engine::from_snapshot(book, snapshot)
+            .map_err(|error| StorageError::Corrupt(format!("snapshot cannot be restored: {}", error)))?,
+        None => Engine::with_book(pair.to_string(), book),
+    };
+    let mut report = RecoveryReport {
+        snapshot_log_seq,
+        skipped_snapshots,
+        replayed_commands: 0,
+        replayed_logs: 0,
+        truncated_bytes: 0,
+    };
+
+    let contents = read_journal(journal_directory)?;
+    if let Some(torn_tail) = &contents.torn_tail {
+        let file = OpenOptions::new().write(true).open(&torn_tail.path)?;
+        report.truncated_bytes = fs::metadata(&torn_tail.path)?.len() - torn_tail.offset;
+        file.set_len(torn_tail.offset)?;
+        file.sync_all()?;
+    }
+
+    let start = engine.book().log_seq();
+    let mut replaying = false;
+    let mut expected: VecDeque<i64> = VecDeque::new(); // Replayed logs not yet matched in the journal.
+    for entry in contents.entries {
+        match entry {
+            JournalEntry::Command { log_seq, command } => {
+                if log_seq < start {
+                    continue; // Already reflected in the snapshot.
+                }
+                expected.clear(); // Logs lost in a crash before the journal was reopened.
+                if log_seq != engine.book().log_seq() {
+                    return Err(StorageError::SequenceMismatch {
+                        expected: engine.book().log_seq(),
+                        actual: log_seq,
+                    });
+                }
+
+                for event in engine.process(command) {
+                    if let Event::Log(log) = event {
+                        expected.push_back(log.get_seq());
+                    }
+                }
+                replaying = true;
+                report.replayed_commands += 1;
+                report.replayed_logs += expected.len();
+            }
+            JournalEntry::Log(log) if replaying => match expected.pop_front() {
+                Some(sequence) if sequence == log.get_seq() => {}
+                Some(sequence) => {
+                    return Err(StorageError::SequenceMismatch { expected: sequence, actual: log.get_seq() });
+                }
+                None => {
+                    return Err(StorageError::SequenceMismatch {
+                        expected: engine.book().log_seq() + 1,
+                        actual: log.get_seq(),
+                    });
+                }
+            },
+            JournalEntry::Log(_) => {} // Produced before the snapshot.
+        }
+    }
+
+    Ok((engine, report))
+}
@@ -1,51 +1,183 @@
+#![allow(dead_code)]
+use crate::core::codec::{crc32, Reader, Writer};
+use crate::core::error::{MatchingError, StorageError};
 use crate::core::limit::Limit;
-use crate::core::order::Order;
+use crate::core::order::{BidOrAsk, Order};
+use rust_decimal::Decimal;
+
+/// The bytes every encoded snapshot starts with.
+pub(crate) const SNAPSHOT_MAGIC: [u8; 4] = *b"MESN";
+/// The snapshot format version written by this build. Version 1 snapshots, which predate the
+/// halted flag, are still read and restore a running book.
+pub(crate) const SNAPSHOT_VERSION: u16 = 2;
 
 #[derive(Debug)]
 pub struct Snapshot {
     pair: String,
 }
 
+/// The state of an order book at a point in time.
+///
+/// Resting orders are listed asks first (best price outwards), then bids (best price outwards),
+/// each limit in queue order, so restoring them in sequence rebuilds the same time priority.
+#[derive(Debug, Clone, PartialEq)]
 pub struct SnapshotData {
     pair: String,
     pub orders: Vec<Order>,
+    pub stop_orders: Vec<Order>,       // Pending stop orders, buy stops first, in activation order.
+    last_trade_price: Option<Decimal>, // The price pending stops are checked against.
+    halted: bool,                      // Whether the book was halted.
     log_seq: i64,
     trade_seq: i64,
 }
 
+impl SnapshotData {
+    /// Returns the pair the snapshot was taken for.
+    pub fn pair(&self) -> &str {
+        &self.pair
+    }
+
+    /// Returns the price of the last trade before the snapshot, if any.
+    pub fn last_trade_price(&self) -> Option<Decimal> {
+        self.last_trade_price
+    }
+
+    /// Returns `true` if the book was halted when the snapshot was taken.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns the sequence of the last log produced before the snapshot.
+    pub fn log_seq(&self) -> i64 {
+        self.log_seq
+    }
+
+    /// Returns the ID of the last trade executed before the snapshot.
+    pub fn trade_seq(&self) -> i64 {
+        self.trade_seq
+    }
+
+    /// Encodes the snapshot in the versioned binary snapshot format.
+    ///
+    /// The encoding starts with a header of the magic bytes `MESN`, the format version (`u16`) and
+    /// a CRC-32 (`u32`) of everything that follows it: the pair, the log and trade sequences, the
+    /// last trade price, the halted flag, the resting orders and the pending stop orders.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Writer::new();
+        body.string(&self.pair);
+        body.i64(self.log_seq);
+        body.i64(self.trade_seq);
+        body.option(self.last_trade_price, Writer::decimal);
+        body.bool(self.halted);
+        for orders in [&self.orders, &self.stop_orders] {
+            body.u32(orders.len() as u32);
+            for order in orders {
+                order.encode(&mut body);
+            }
+        }
+        let body = body.into_bytes();
+
+        let mut header = Writer::new();
+        header.u16(SNAPSHOT_VERSION);
+        header.u32(crc32(&body));
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend(header.into_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    /// Decodes a snapshot written by `encode`.
+    ///
+    /// # Returns
+    /// * The snapshot, or a `StorageError` if the bytes are not a snapshot, were written in an
+    ///   unsupported format version, fail their checksum or are otherwise malformed.
+    pub fn decode(bytes: &[u8]) -> Result<SnapshotData, StorageError> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(SNAPSHOT_MAGIC.len()).ok() != Some(&SNAPSHOT_MAGIC[..]) {
+            return Err(StorageError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if !(1..=SNAPSHOT_VERSION).contains(&version) {
+            return Err(StorageError::UnsupportedVersion(version));
+        }
+        let expected = reader.u32()?;
+        let body = reader.take(reader.remaining())?;
+        let actual = crc32(body);
+        if actual != expected {
+            return Err(StorageError::ChecksumMismatch { expected, actual });
+        }
+
+        let mut reader = Reader::new(body);
+        let pair = reader.string()?;
+        let log_seq = reader.i64()?;
+        let trade_seq = reader.i64()?;
+        let last_trade_price = reader.option(Reader::decimal)?;
+        let halted = version >= 2 && reader.bool()?;
+        let mut lists = [vec![], vec![]];
+        for orders in &mut lists {
+            for _ in 0..reader.u32()? {
+                orders.push(Order::decode(&mut reader)?);
+            }
+        }
+        if reader.remaining() > 0 {
+            return Err(StorageError::Corrupt(format!("{} trailing bytes", reader.remaining())));
+        }
+        let [orders, stop_orders] = lists;
+
+        Ok(SnapshotData {
+            pair,
+            orders,
+            stop_orders,
+            last_trade_price,
+            halted,
+            log_seq,
+            trade_seq,
+        })
+    }
+}
+
 impl Snapshot {
     pub fn new(pair: String) -> Self {
         Self { pair }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn construct_snapshot(
         &self,
         ask: Vec<Limit>,
         bid: Vec<Limit>,
+        stop_orders: Vec<Order>,
+        last_trade_price: Option<Decimal>,
+        halted: bool,
         trade_seq: i64,
         log_seq: i64,
-    ) -> SnapshotData {
-        let mut i = 0;
-
+    ) -> Result<SnapshotData, MatchingError> {
         let mut snapshot_data = SnapshotData {
             orders: vec![],
             pair: self.pair.clone(),
+            stop_orders,
+            last_trade_price,
+            halted,
             trade_seq,
             log_seq,
         };
         for limit in ask {
             for order in limit.orders {
-                i += 1;
-                snapshot_data.orders[i] = order;
+                if !matches!(order.bid_or_ask, BidOrAsk::Ask) {
+                    return Err(MatchingError::SideMismatch(order.id));
+                }
+                snapshot_data.orders.push(order);
             }
         }
 
         for limit in bid {
             for order in limit.orders {
-                i += 1;
-                snapshot_data.orders[i] = order;
+                if !matches!(order.bid_or_ask, BidOrAsk::Bid) {
+                    return Err(MatchingError::SideMismatch(order.id));
+                }
+                snapshot_data.orders.push(order);
             }
         }
-        snapshot_data
+        Ok(snapshot_data)
     }
 }
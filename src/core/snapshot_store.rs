@@ -0,0 +1,145 @@
+#![allow(dead_code)]
+use crate::core::error::StorageError;
+use crate::core::snapshot::SnapshotData;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+/// Keeps encoded snapshots in a directory, one file per snapshot.
+///
+/// Files are named `<pair>.<log sequence>.snapshot`, with the pair escaped by `file_key` and the
+/// log sequence zero-padded, so the latest snapshot of a pair is the one with the highest log
+/// sequence.
+#[derive(Debug)]
+pub struct SnapshotStore {
+    directory: PathBuf, // The directory holding the snapshot files.
+}
+
+impl SnapshotStore {
+    /// Opens the store in `directory`, creating the directory if it does not exist.
+    pub fn new(directory: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    /// Writes a snapshot to the store.
+    ///
+    /// The snapshot is written to a temporary file, flushed to disk and then renamed into place,
+    /// so a crash never leaves a partially written snapshot under its final name.
+    ///
+    /// # Returns
+    /// * The path of the written file, or the `StorageError` that prevented it from being written.
+    pub fn save(&self, snapshot: &SnapshotData) -> Result<PathBuf, StorageError> {
+        let path = self
+            .directory
+            .join(format!("{}.{:020}.{}", file_key(snapshot.pair()), snapshot.log_seq(), SNAPSHOT_EXTENSION));
+        let temporary = path.with_extension("tmp");
+
+        let mut file = File::create(&temporary)?;
+        file.write_all(&snapshot.encode())?;
+        file.sync_all()?;
+        fs::rename(&temporary, &path)?;
+        sync_directory(&self.directory)?;
+        Ok(path)
+    }
+
+    /// Loads the latest snapshot of `pair`.
+    ///
+    /// # Returns
+    /// * The snapshot with the highest log sequence, `None` if the store holds no snapshot of the
+    ///   pair, or a `StorageError` if the latest snapshot cannot be read. A corrupt latest snapshot
+    ///   is reported rather than skipped, so recovery never silently starts from older state.
+    pub fn load_latest(&self, pair: &str) -> Result<Option<SnapshotData>, StorageError> {
+        match self.snapshot_files(pair)?.pop() {
+            Some((_, path)) => read_pair_snapshot(&path, pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Loads the newest snapshot of `pair` that can be read, skipping damaged or incompatible
+    /// ones.
+    ///
+    /// # Returns
+    /// * The snapshot, or `None` if no readable snapshot of the pair exists, along with the number
+    ///   of newer snapshots that were skipped. I/O errors are returned rather than skipped.
+    pub fn load_newest_valid(&self, pair: &str) -> Result<(Option<SnapshotData>, usize), StorageError> {
+        let mut skipped = 0;
+        for (_, path) in self.snapshot_files(pair)?.into_iter().rev() {
+            match read_pair_snapshot(&path, pair) {
+                Ok(snapshot) => return Ok((Some(snapshot), skipped)),
+                Err(StorageError::Io(error)) => return Err(StorageError::Io(error)),
+                Err(_) => skipped += 1,
+            }
+        }
+        Ok((None, skipped))
+    }
+
+    /// Lists the snapshot files of `pair`, lowest log sequence first.
+    fn snapshot_files(&self, pair: &str) -> Result<Vec<(i64, PathBuf)>, StorageError> {
+        let prefix = format!("{}.", file_key(pair));
+        let suffix = format!(".{}", SNAPSHOT_EXTENSION);
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let sequence = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .and_then(|sequence| sequence.parse::<i64>().ok());
+            if let Some(sequence) = sequence {
+                files.push((sequence, path));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// Reads the snapshot file at `path`, checking that it belongs to `pair`.
+fn read_pair_snapshot(path: &Path, pair: &str) -> Result<SnapshotData, StorageError> {
+    let snapshot = read_snapshot(path)?;
+    if snapshot.pair() != pair {
+        return Err(StorageError::Corrupt(format!(
+            "{} holds a snapshot of {}, not {}",
+            path.display(),
+            snapshot.pair(),
+            pair
+        )));
+    }
+    Ok(snapshot)
+}
+
+/// Reads and decodes the snapshot file at `path`.
+pub fn read_snapshot(path: &Path) -> Result<SnapshotData, StorageError> {
+    SnapshotData::decode(&fs::read(path)?)
+}
+
+/// Turns a pair into a string that is safe to use in a file name.
+///
+/// ASCII letters, digits and `-` are kept; every other byte, including `_` itself, is written as
+/// `_` followed by its two hex digits. Distinct pairs therefore never share a key, e.g. "BTC/USD"
+/// becomes `BTC_2FUSD` and "BTC_USD" becomes `BTC_5FUSD`.
+pub(crate) fn file_key(pair: &str) -> String {
+    let mut key = String::with_capacity(pair.len());
+    for byte in pair.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            key.push(byte as char);
+        } else {
+            key.push_str(&format!("_{:02X}", byte));
+        }
+    }
+    key
+}
+
+/// Flushes a directory so that a rename inside it survives a crash.
+pub(crate) fn sync_directory(directory: &Path) -> Result<(), StorageError> {
+    #[cfg(unix)]
+    File::open(directory)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = directory; // Directories cannot be opened for syncing on this platform.
+    Ok(())
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests_candles {
+    use crate::core::candles::{Candle, CandleAggregator, Interval};
+    use crate::core::engine::{Command, Engine};
+    use crate::core::order::{BidOrAsk, Order};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::time::{Duration, SystemTime};
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn ohlcv(candle: &Candle) -> (u64, Decimal, Decimal, Decimal, Decimal, Decimal, usize) {
+        let start = candle.start.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        (start, candle.open, candle.high, candle.low, candle.close, candle.volume, candle.trade_count)
+    }
+
+    #[test]
+    fn test_builds_candles_per_interval() {
+        let mut aggregator = CandleAggregator::new(&[Interval::OneMinute, Interval::FiveMinutes], 10);
+        aggregator.on_trade("BTC/USD", dec!(100), dec!(1), at(60));
+        aggregator.on_trade("BTC/USD", dec!(105), dec!(2), at(80));
+        aggregator.on_trade("BTC/USD", dec!(98), dec!(1), at(100));
+
+        let finalized = aggregator.on_trade("BTC/USD", dec!(101), dec!(3), at(125));
+
+        assert_eq!(finalized.iter().map(ohlcv).collect::<Vec<_>>(), vec![(60, dec!(100), dec!(105), dec!(98), dec!(98), dec!(4), 3)]);
+        let current = aggregator.current("BTC/USD", Interval::OneMinute).unwrap();
+        assert_eq!(ohlcv(current), (120, dec!(101), dec!(101), dec!(101), dec!(101), dec!(3), 1));
+        let five_minutes = aggregator.current("BTC/USD", Interval::FiveMinutes).unwrap();
+        assert_eq!(ohlcv(five_minutes), (0, dec!(100), dec!(105), dec!(98), dec!(101), dec!(7), 4));
+        assert!(aggregator.current("ETH/USD", Interval::OneMinute).is_none());
+    }
+
+    #[test]
+    fn test_fills_empty_intervals() {
+        let mut aggregator = CandleAggregator::new(&[Interval::OneSecond], 10);
+        aggregator.on_trade("BTC/USD", dec!(100), dec!(1), at(10));
+
+        let finalized = aggregator.on_trade("BTC/USD", dec!(102), dec!(1), at(13));
+
+        assert_eq!(
+            finalized.iter().map(ohlcv).collect::<Vec<_>>(),
+            vec![
+                (10, dec!(100), dec!(100), dec!(100), dec!(100), dec!(1), 1),
+                (11, dec!(100), dec!(100), dec!(100), dec!(100), dec!(0), 0),
+                (12, dec!(100), dec!(100), dec!(100), dec!(100), dec!(0), 0),
+            ]
+        );
+        assert_eq!(aggregator.current("BTC/USD", Interval::OneSecond).unwrap().open, dec!(102));
+    }
+
+    #[test]
+    fn test_ticks_close_candles_of_quiet_markets() {
+        let mut aggregator = CandleAggregator::new(&[Interval::OneMinute, Interval::OneHour], 2);
+        aggregator.on_trade("BTC/USD", dec!(100), dec!(1), at(3_600));
+        aggregator.on_trade("ETH/USD", dec!(10), dec!(5), at(3_630));
+
+        assert!(aggregator.tick(at(3_659)).is_empty(), "Expected no candle to close before its end");
+        let finalized = aggregator.tick(at(3_779));
+
+        let closed: Vec<_> = finalized.iter().map(|candle| (candle.symbol.as_str(), ohlcv(candle).0, candle.trade_count)).collect();
+        assert_eq!(closed, vec![("BTC/USD", 3_600, 1), ("BTC/USD", 3_660, 0), ("ETH/USD", 3_600, 1), ("ETH/USD", 3_660, 0)]);
+        let current = aggregator.current("BTC/USD", Interval::OneMinute).unwrap();
+        assert_eq!((ohlcv(current).0, current.trade_count), (3_720, 0), "Expected a flat candle in progress");
+
+        aggregator.on_trade("BTC/USD", dec!(99), dec!(1), at(3_790));
+        aggregator.tick(at(3_840));
+        let history: Vec<_> = aggregator.finalized("BTC/USD", Interval::OneMinute).map(|candle| ohlcv(candle).0).collect();
+        assert_eq!(history, vec![3_720, 3_780], "Expected only the most recent candles to be kept");
+        assert_eq!(aggregator.current("BTC/USD", Interval::OneHour).unwrap().volume, dec!(2));
+    }
+
+    #[test]
+    fn test_caps_flat_candles_after_long_gap() {
+        let mut aggregator = CandleAggregator::new(&[Interval::OneSecond], 3);
+        aggregator.on_trade("BTC/USD", dec!(100), dec!(1), at(10));
+
+        let finalized = aggregator.tick(at(1_000_000_000));
+
+        let starts: Vec<_> = finalized.iter().map(|candle| (ohlcv(candle).0, candle.trade_count)).collect();
+        assert_eq!(starts, vec![(10, 1), (999_999_997, 0), (999_999_998, 0), (999_999_999, 0)]);
+        let history: Vec<_> = aggregator.finalized("BTC/USD", Interval::OneSecond).map(|candle| ohlcv(candle).0).collect();
+        assert_eq!(history, vec![999_999_997, 999_999_998, 999_999_999]);
+        let current = aggregator.current("BTC/USD", Interval::OneSecond).unwrap();
+        assert_eq!(ohlcv(current), (1_000_000_000, dec!(100), dec!(100), dec!(100), dec!(100), dec!(0), 0));
+    }
+
+    #[test]
+    fn test_aligns_daily_candles_to_midnight() {
+        let day = Interval::OneDay.duration().as_secs();
+
+        assert_eq!(Interval::OneDay.start_of(at(3 * day + 5_000)), at(3 * day));
+        assert_eq!(Interval::FiveMinutes.start_of(at(899)), at(600));
+        assert_eq!(Interval::ALL.len(), 5);
+    }
+
+    #[test]
+    fn test_consumes_engine_trades() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+        let mut aggregator = CandleAggregator::new(&Interval::ALL, 10);
+        engine.process(Command::NewOrder(Order::new("1".to_string(), BidOrAsk::Ask, dec!(101), dec!(2))));
+        engine.process(Command::NewOrder(Order::new("2".to_string(), BidOrAsk::Ask, dec!(102), dec!(2))));
+
+        let events = engine.process(Command::NewOrder(Order::new("3".to_string(), BidOrAsk::Bid, dec!(102), dec!(3))));
+        aggregator.on_events("BTC/USD", &events);
+
+        let candle = aggregator.current("BTC/USD", Interval::OneDay).unwrap();
+        assert_eq!((candle.open, candle.high, candle.low, candle.close), (dec!(101), dec!(102), dec!(101), dec!(102)));
+        assert_eq!((candle.volume, candle.trade_count), (dec!(3), 2));
+    }
+}
@@ -0,0 +1,173 @@
+#[cfg(test)]
+mod tests_engine {
+    use crate::core::engine::{Command, Engine, Event};
+    use crate::core::error::MatchingError;
+    use crate::core::log::ReceivedLog;
+    use crate::core::order::{BidOrAsk, Order, OrderType, TimeInForce};
+    use crate::core::tests::support::new_order;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::time::{Duration, SystemTime};
+
+    fn resting_orders(engine: &Engine) -> Vec<(String, Decimal, Decimal)> {
+        engine
+            .book()
+            .ask_limits()
+            .chain(engine.book().bid_limits().rev())
+            .flat_map(|limit| limit.orders.iter())
+            .map(|order| (order.id.clone(), order.price, order.size))
+            .collect()
+    }
+
+    #[test]
+    fn test_new_order_rests_at_its_own_price() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+
+        let events = engine.process(new_order("1", "alice", BidOrAsk::Bid, dec!(100), dec!(5)));
+
+        assert_eq!(events.len(), 2, "Expected a received log and an open log");
+        assert!(matches!(events[1], Event::Log(_)));
+        assert_eq!(engine.book().best_bid().map(|limit| limit.price), Some(dec!(100)));
+    }
+
+    #[test]
+    fn test_every_order_is_acknowledged_before_matching() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+        engine.process(new_order("1", "alice", BidOrAsk::Ask, dec!(101), dec!(5)));
+
+        let events = engine.process(Command::NewOrder(
+            Order::new("2".to_string(), BidOrAsk::Bid, dec!(0), dec!(2)).with_order_type(OrderType::Market),
+        ));
+        let refused = engine.process(new_order("1", "bob", BidOrAsk::Bid, dec!(99), dec!(1)));
+
+        let Event::Log(log) = &events[0] else {
+            panic!("Expected the received log first");
+        };
+        assert_eq!(log.get_seq(), 3);
+        let received = log.as_any().downcast_ref::<ReceivedLog>().expect("Expected a received log");
+        assert_eq!(
+            (received.order_id.as_str(), &received.bid_or_ask, &received.order_type, received.size),
+            ("2", &BidOrAsk::Bid, &OrderType::Market, dec!(2))
+        );
+        assert!(events[1..].iter().all(|event| matches!(event, Event::Log(log) if log.get_seq() > 3)), "Expected the fills to follow the receipt");
+        assert!(matches!(
+            &refused[..],
+            [Event::Log(log), Event::Error(MatchingError::DuplicateOrderId(_))] if log.as_any().is::<ReceivedLog>()
+        ));
+    }
+
+    #[test]
+    fn test_refused_command_reports_error() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+
+        let events = engine.process(Command::Cancel { order_id: "missing".to_string() });
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], Event::Error(MatchingError::UnknownOrder(id)) if id == "missing"));
+    }
+
+    #[test]
+    fn test_halt_and_resume() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+
+        assert!(matches!(engine.process(Command::Halt)[..], [Event::Halted]));
+        let events = engine.process(new_order("1", "alice", BidOrAsk::Bid, dec!(100), dec!(5)));
+        assert!(matches!(events[..], [Event::Log(_), Event::Error(MatchingError::BookHalted)]));
+
+        assert!(matches!(engine.process(Command::Resume)[..], [Event::Resumed]));
+        let events = engine.process(new_order("1", "alice", BidOrAsk::Bid, dec!(100), dec!(5)));
+        assert!(matches!(events[..], [Event::Log(_), Event::Log(_)]));
+    }
+
+    #[test]
+    fn test_amend_and_cancel() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+        engine.process(new_order("1", "alice", BidOrAsk::Ask, dec!(101), dec!(5)));
+
+        engine.process(Command::Amend { order_id: "1".to_string(), price: dec!(102), size: dec!(3) });
+        assert_eq!(resting_orders(&engine), vec![("1".to_string(), dec!(102), dec!(3))]);
+
+        let events = engine.process(Command::Cancel { order_id: "1".to_string() });
+        assert!(matches!(events[..], [Event::Log(_)]));
+        assert!(engine.book().get_order("1").is_none());
+    }
+
+    #[test]
+    fn test_mass_cancel_by_owner_and_side() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+        engine.process(new_order("1", "alice", BidOrAsk::Bid, dec!(99), dec!(1)));
+        engine.process(new_order("2", "alice", BidOrAsk::Ask, dec!(101), dec!(1)));
+        engine.process(new_order("3", "bob", BidOrAsk::Bid, dec!(98), dec!(1)));
+        engine.process(Command::NewOrder(
+            Order::new("4".to_string(), BidOrAsk::Bid, dec!(0), dec!(1))
+                .with_owner("alice".to_string())
+                .with_order_type(OrderType::StopMarket(dec!(105))),
+        ));
+
+        let events = engine.process(Command::MassCancel {
+            owner: Some("alice".to_string()),
+            bid_or_ask: Some(BidOrAsk::Bid),
+        });
+
+        assert_eq!(events.len(), 2, "Expected the resting bid and the pending stop to be canceled");
+        assert!(engine.book().get_order("1").is_none());
+        assert!(engine.book().get_order("4").is_none());
+        assert!(engine.book().get_order("2").is_some());
+        assert!(engine.book().get_order("3").is_some());
+
+        let events = engine.process(Command::MassCancel { owner: None, bid_or_ask: None });
+        assert_eq!(events.len(), 2);
+        assert!(resting_orders(&engine).is_empty());
+    }
+
+    #[test]
+    fn test_expire_uses_command_time() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+        let expire_at = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        engine.process(Command::NewOrder(
+            Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(1))
+                .with_time_in_force(TimeInForce::GoodTillDate(expire_at)),
+        ));
+
+        let events = engine.process(Command::Expire { now: expire_at - Duration::from_secs(1) });
+        assert!(events.is_empty());
+
+        let events = engine.process(Command::Expire { now: expire_at });
+        assert_eq!(events.len(), 1);
+        assert!(engine.book().get_order("1").is_none());
+    }
+
+    #[test]
+    fn test_snapshot_leaves_book_intact() {
+        let mut engine = Engine::new("BTC/USD".to_string());
+        engine.process(new_order("1", "alice", BidOrAsk::Bid, dec!(100), dec!(5)));
+
+        let events = engine.process(Command::Snapshot);
+
+        assert!(matches!(&events[..], [Event::Snapshot(snapshot)] if snapshot.orders.len() == 1));
+        assert!(engine.book().get_order("1").is_some());
+    }
+
+    #[test]
+    fn test_replay_rebuilds_same_book() {
+        let commands = vec![
+            new_order("1", "alice", BidOrAsk::Ask, dec!(101), dec!(5)),
+            new_order("2", "bob", BidOrAsk::Ask, dec!(102), dec!(3)),
+            new_order("3", "carol", BidOrAsk::Bid, dec!(99), dec!(4)),
+            new_order("4", "dave", BidOrAsk::Bid, dec!(101.5), dec!(6)),
+            Command::Amend { order_id: "3".to_string(), price: dec!(100), size: dec!(2) },
+            Command::Cancel { order_id: "unknown".to_string() },
+            Command::Cancel { order_id: "2".to_string() },
+            new_order("5", "erin", BidOrAsk::Ask, dec!(100), dec!(2)),
+        ];
+
+        let mut first = Engine::new("BTC/USD".to_string());
+        let mut second = Engine::new("BTC/USD".to_string());
+        let first_events: Vec<usize> = commands.iter().map(|command| first.process(command.clone()).len()).collect();
+        let second_events: Vec<usize> = commands.into_iter().map(|command| second.process(command).len()).collect();
+
+        assert_eq!(first_events, second_events);
+        assert_eq!(resting_orders(&first), resting_orders(&second));
+        assert!(!resting_orders(&first).is_empty());
+    }
+}
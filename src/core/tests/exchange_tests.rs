@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests_exchange {
+    use crate::core::engine::{Command, Engine, Event};
+    use crate::core::error::MatchingError;
+    use crate::core::exchange::Exchange;
+    use crate::core::instrument::{InstrumentConfig, InstrumentSpec};
+    use crate::core::log::{RejectLog, RejectReason};
+    use crate::core::order::{BidOrAsk, SelfTradePrevention};
+    use crate::core::tests::support::new_order;
+    use rust_decimal_macros::dec;
+
+    fn exchange() -> Exchange {
+        let mut exchange = Exchange::new();
+        exchange.list("BTC/USD", InstrumentConfig::default()).unwrap();
+        exchange
+            .list(
+                "ETH/USD",
+                InstrumentConfig {
+                    spec: InstrumentSpec { tick_size: dec!(0.5), ..InstrumentSpec::default() },
+                    self_trade_prevention: Some(SelfTradePrevention::CancelNewest),
+                },
+            )
+            .unwrap();
+        exchange
+    }
+
+    #[test]
+    fn test_routes_commands_by_symbol() {
+        let mut exchange = exchange();
+
+        exchange.process("BTC/USD", new_order("1", "alice", BidOrAsk::Bid, dec!(100), dec!(1)));
+        exchange.process("ETH/USD", new_order("1", "alice", BidOrAsk::Ask, dec!(200), dec!(2)));
+
+        let btc = exchange.engine("BTC/USD").unwrap().book();
+        let eth = exchange.engine("ETH/USD").unwrap().book();
+        assert_eq!(btc.best_bid().map(|limit| limit.price), Some(dec!(100)));
+        assert!(btc.best_ask().is_none());
+        assert_eq!(eth.best_ask().map(|limit| limit.price), Some(dec!(200)));
+        assert!(eth.best_bid().is_none(), "Expected the same order ID to be independent per instrument");
+        assert_eq!(exchange.symbols().collect::<Vec<_>>(), vec!["BTC/USD", "ETH/USD"]);
+    }
+
+    #[test]
+    fn test_applies_per_instrument_config() {
+        let mut exchange = exchange();
+
+        let btc = exchange.process("BTC/USD", new_order("1", "alice", BidOrAsk::Bid, dec!(100.25), dec!(1)));
+        let eth = exchange.process("ETH/USD", new_order("1", "alice", BidOrAsk::Bid, dec!(100.25), dec!(1)));
+
+        assert!(exchange.engine("BTC/USD").unwrap().book().get_order("1").is_some());
+        assert!(matches!(
+            &eth[..],
+            [_, Event::Log(log)] if log.as_any().downcast_ref::<RejectLog>().is_some_and(|log| log.reason == RejectReason::PriceNotOnTick)
+        ));
+        assert_eq!(btc.len(), 2);
+
+        exchange.process("ETH/USD", new_order("2", "alice", BidOrAsk::Bid, dec!(100), dec!(1)));
+        exchange.process("ETH/USD", new_order("3", "alice", BidOrAsk::Ask, dec!(100), dec!(1)));
+        let eth = exchange.engine("ETH/USD").unwrap().book();
+        assert!(eth.get_order("2").is_some(), "Expected self-trade prevention to keep the resting order");
+        assert!(eth.get_order("3").is_none());
+        assert_eq!(exchange.config("ETH/USD").unwrap().self_trade_prevention, Some(SelfTradePrevention::CancelNewest));
+    }
+
+    #[test]
+    fn test_list_and_delist_at_runtime() {
+        let mut exchange = exchange();
+        exchange.process("BTC/USD", new_order("1", "alice", BidOrAsk::Bid, dec!(100), dec!(1)));
+
+        assert_eq!(
+            exchange.list("BTC/USD", InstrumentConfig::default()),
+            Err(MatchingError::DuplicateInstrument("BTC/USD".to_string()))
+        );
+        let delisted = exchange.delist("BTC/USD").unwrap();
+        assert!(delisted.book().get_order("1").is_some(), "Expected the delisted book to keep its orders");
+        assert!(matches!(
+            &exchange.process("BTC/USD", Command::Halt)[..],
+            [Event::Error(MatchingError::UnknownInstrument(symbol))] if symbol == "BTC/USD"
+        ));
+        assert_eq!(exchange.delist("BTC/USD").unwrap_err(), MatchingError::UnknownInstrument("BTC/USD".to_string()));
+
+        let strict = InstrumentConfig {
+            self_trade_prevention: Some(SelfTradePrevention::CancelNewest),
+            ..InstrumentConfig::default()
+        };
+        let ticked = InstrumentConfig {
+            spec: InstrumentSpec { tick_size: dec!(0.5), ..InstrumentSpec::default() },
+            ..InstrumentConfig::default()
+        };
+        let mismatch = Err(MatchingError::ConfigMismatch("BTC/USD".to_string()));
+        assert_eq!(exchange.list_engine(strict, Engine::new("BTC/USD".to_string())), mismatch);
+        assert_eq!(exchange.list_engine(ticked, Engine::new("BTC/USD".to_string())), mismatch);
+        assert!(exchange.engine("BTC/USD").is_none(), "Expected a mismatched engine not to be listed");
+        exchange.list_engine(InstrumentConfig::default(), delisted).unwrap();
+        exchange.list("SOL/USD", InstrumentConfig::default()).unwrap();
+        assert!(exchange.engine("BTC/USD").unwrap().book().get_order("1").is_some());
+        assert_eq!(exchange.symbols().collect::<Vec<_>>(), vec!["BTC/USD", "ETH/USD", "SOL/USD"]);
+    }
+
+    #[test]
+    fn test_snapshot_all_books() {
+        let mut exchange = exchange();
+        exchange.process("BTC/USD", new_order("1", "alice", BidOrAsk::Bid, dec!(100), dec!(1)));
+        exchange.process("ETH/USD", new_order("2", "alice", BidOrAsk::Ask, dec!(200), dec!(2)));
+        exchange.process("ETH/USD", Command::Halt);
+
+        let snapshots = exchange.snapshot_all().unwrap();
+
+        assert_eq!(snapshots.iter().map(|snapshot| snapshot.pair()).collect::<Vec<_>>(), vec!["BTC/USD", "ETH/USD"]);
+        let mut restored = Exchange::new();
+        for snapshot in snapshots.clone() {
+            let config = exchange.config(snapshot.pair()).unwrap().clone();
+            restored.list_from_snapshot(config, snapshot).unwrap();
+        }
+        assert_eq!(restored.snapshot_all().unwrap(), snapshots);
+        assert!(restored.engine("ETH/USD").unwrap().book().is_halted());
+        let duplicate = Engine::new("ETH/USD".to_string());
+        assert_eq!(
+            restored.list_engine(InstrumentConfig::default(), duplicate),
+            Err(MatchingError::DuplicateInstrument("ETH/USD".to_string()))
+        );
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests_instrument {
+    use crate::core::instrument::InstrumentSpec;
+    use crate::core::log::RejectReason;
+    use crate::core::order::{BidOrAsk, Order, OrderType};
+    use rust_decimal_macros::dec;
+
+    // Helper function to create a strict specification
+    fn create_spec() -> InstrumentSpec {
+        InstrumentSpec {
+            tick_size: dec!(0.05),
+            lot_size: Some(dec!(0.1)),
+            min_size: Some(dec!(1)),
+            max_size: Some(dec!(1000)),
+            min_notional: Some(dec!(100)),
+            price_precision: Some(2),
+        }
+    }
+
+    #[test]
+    fn test_validate_accepts_conforming_order() {
+        let order = Order::new("1".to_string(), BidOrAsk::Bid, dec!(100.05), dec!(2.5));
+
+        assert_eq!(create_spec().validate(&order, Some(order.price)), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_price() {
+        let spec = create_spec();
+
+        assert_eq!(spec.validate_price(dec!(100.03)), Err(RejectReason::PriceNotOnTick));
+        assert_eq!(spec.validate_price(dec!(100.001)), Err(RejectReason::PricePrecisionExceeded));
+        assert_eq!(spec.validate_price(dec!(100.100)), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_size() {
+        let spec = create_spec();
+
+        assert_eq!(spec.validate_size(dec!(2.55)), Err(RejectReason::SizeNotOnLot));
+        assert_eq!(spec.validate_size(dec!(0.5)), Err(RejectReason::SizeBelowMinimum));
+        assert_eq!(spec.validate_size(dec!(1000.1)), Err(RejectReason::SizeAboveMaximum));
+    }
+
+    #[test]
+    fn test_validate_notional_and_stop_price() {
+        let spec = create_spec();
+        let small = Order::new("1".to_string(), BidOrAsk::Bid, dec!(10), dec!(2));
+        let stop = Order::new("2".to_string(), BidOrAsk::Ask, dec!(0), dec!(2)).with_order_type(OrderType::StopMarket(dec!(99.99)));
+        let market = Order::new("3".to_string(), BidOrAsk::Ask, dec!(0), dec!(2));
+
+        assert_eq!(spec.validate(&small, Some(small.price)), Err(RejectReason::NotionalBelowMinimum));
+        assert_eq!(spec.validate(&stop, None), Err(RejectReason::PriceNotOnTick));
+        assert_eq!(spec.validate(&market, None), Ok(()), "Expected market orders to skip price rules");
+    }
+
+    #[test]
+    fn test_reason_codes() {
+        assert_eq!(RejectReason::PriceNotOnTick.code(), "PRICE_NOT_ON_TICK");
+        assert_eq!(RejectReason::NotionalBelowMinimum.code(), "NOTIONAL_BELOW_MINIMUM");
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests_journal {
+    use crate::core::engine::{Command, Engine, Event};
+    use crate::core::error::StorageError;
+    use crate::core::journal::{read_journal, Journal, JournalConfig, JournalEntry, SyncPolicy};
+    use crate::core::log::{MatchLog, OpenLog, ReceivedLog};
+    use crate::core::order::BidOrAsk;
+    use crate::core::tests::support::{new_order, TestDirectory};
+    use rust_decimal_macros::dec;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    // The paths of the segment files in `directory`, oldest first
+    fn segment_files(directory: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_engine_journals_commands_and_logs() {
+        let directory = TestDirectory::new("journal-engine");
+        let journal = Journal::open(directory.path(), JournalConfig::default()).unwrap();
+        let mut engine = Engine::new("BTC/USD".to_string()).with_journal(journal);
+        engine.process(new_order("1", "alice", BidOrAsk::Ask, dec!(100), dec!(5)));
+        let events = engine.process(new_order("2", "alice", BidOrAsk::Bid, dec!(100), dec!(5)));
+        assert!(events.iter().all(|event| matches!(event, Event::Log(_))));
+        drop(engine);
+
+        let contents = read_journal(directory.path()).unwrap();
+
+        assert!(contents.torn_tail.is_none());
+        assert_eq!(contents.entries.len(), 7, "Expected two commands, two receipts, an open, a match and a done log");
+        assert!(matches!(&contents.entries[0], JournalEntry::Command { log_seq: 0, command: Command::NewOrder(order) } if order.id == "1"));
+        assert!(matches!(&contents.entries[1], JournalEntry::Log(log) if log.as_any().is::<ReceivedLog>() && log.get_seq() == 1));
+        assert!(matches!(&contents.entries[2], JournalEntry::Log(log) if log.as_any().is::<OpenLog>() && log.get_seq() == 2));
+        assert!(matches!(&contents.entries[3], JournalEntry::Command { log_seq: 2, .. }));
+        let JournalEntry::Log(log) = &contents.entries[5] else {
+            panic!("Expected a log");
+        };
+        let trade = log.as_any().downcast_ref::<MatchLog>().expect("Expected a match log");
+        assert_eq!((trade.trade_id, trade.size, trade.maker_order_id.as_str()), (1, dec!(5), "1"));
+    }
+
+    #[test]
+    fn test_rotates_segments() {
+        let directory = TestDirectory::new("journal-rotate");
+        let config = JournalConfig {
+            max_segment_bytes: 64,
+            ..JournalConfig::default()
+        };
+        let mut journal = Journal::open(directory.path(), config.clone()).unwrap();
+        for id in 0..10 {
+            journal.append_command(id, &Command::Cancel { order_id: id.to_string() }).unwrap();
+        }
+        drop(journal);
+        let mut reopened = Journal::open(directory.path(), config).unwrap();
+        reopened.append_command(10, &Command::Halt).unwrap();
+        drop(reopened);
+
+        let contents = read_journal(directory.path()).unwrap();
+        let log_seqs: Vec<i64> = contents
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                JournalEntry::Command { log_seq, .. } => *log_seq,
+                JournalEntry::Log(log) => log.get_seq(),
+            })
+            .collect();
+
+        assert!(segment_files(directory.path()).len() > 2, "Expected the journal to span several segments");
+        assert!(segment_files(directory.path()).iter().all(|path| fs::metadata(path).unwrap().len() <= 64));
+        assert_eq!(log_seqs, (0..=10).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn test_reports_torn_tail() {
+        let directory = TestDirectory::new("journal-torn");
+        let mut journal = Journal::open(directory.path(), JournalConfig::default()).unwrap();
+        journal.append_command(0, &Command::Halt).unwrap();
+        journal.append_command(0, &Command::Resume).unwrap();
+        drop(journal);
+        let path = segment_files(directory.path()).pop().unwrap();
+        let length = fs::metadata(&path).unwrap().len();
+        let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(length - 3).unwrap(); // Cut the last record short
+
+        let contents = read_journal(directory.path()).unwrap();
+
+        assert_eq!(contents.entries.len(), 1);
+        let torn_tail = contents.torn_tail.expect("Expected a torn tail");
+        assert_eq!(torn_tail.path, path);
+        assert_eq!(torn_tail.offset, length - 18, "Expected the intact part to end after the first record");
+    }
+
+    #[test]
+    fn test_detects_corrupt_record() {
+        let directory = TestDirectory::new("journal-corrupt");
+        let mut journal = Journal::open(directory.path(), JournalConfig::default()).unwrap();
+        journal.append_command(0, &Command::Halt).unwrap();
+        journal.append_command(0, &Command::Resume).unwrap();
+        drop(journal);
+        let path = segment_files(directory.path()).pop().unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[6 + 8 + 2] ^= 0xFF; // Flip a byte inside the first record
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(matches!(read_journal(directory.path()), Err(StorageError::ChecksumMismatch { .. })));
+        bytes[0] = b'X';
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(read_journal(directory.path()), Err(StorageError::BadMagic)));
+    }
+
+    #[test]
+    fn test_sync_policies() {
+        let directory = TestDirectory::new("journal-sync");
+        let batched = JournalConfig {
+            sync_policy: SyncPolicy::Batched(3),
+            ..JournalConfig::default()
+        };
+        let mut journal = Journal::open(directory.path(), batched).unwrap();
+        journal.append_command(0, &Command::Halt).unwrap();
+        journal.append_command(0, &Command::Resume).unwrap();
+        assert_eq!(journal.unsynced(), 2);
+        journal.append_command(0, &Command::Halt).unwrap();
+        assert_eq!(journal.unsynced(), 0);
+        drop(journal);
+
+        let interval = JournalConfig {
+            sync_policy: SyncPolicy::Interval(Duration::from_secs(3600)),
+            ..JournalConfig::default()
+        };
+        let mut journal = Journal::open(directory.path(), interval).unwrap();
+        journal.append_command(0, &Command::Halt).unwrap();
+        journal.append_command(0, &Command::Resume).unwrap();
+        assert_eq!(journal.unsynced(), 2);
+        journal.sync().unwrap();
+        assert_eq!(journal.unsynced(), 0);
+
+        let mut every = Journal::open(directory.path(), JournalConfig::default()).unwrap();
+        every.append_command(0, &Command::Halt).unwrap();
+        assert_eq!(every.unsynced(), 0);
+    }
+}
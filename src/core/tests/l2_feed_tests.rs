@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests_l2_feed {
+    use crate::core::engine::Command;
+    use crate::core::error::FeedError;
+    use crate::core::l2_feed::{L2Book, L2Publisher, LevelUpdate};
+    use crate::core::order::{BidOrAsk, Order};
+    use crate::core::tests::support::{engine, random_commands};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_updates_rebuild_the_book() {
+        for seed in 0..50 {
+            let mut engine = engine(seed);
+            let mut publisher = L2Publisher::new();
+            let mut client = L2Book::from_snapshot(publisher.snapshot());
+
+            for command in random_commands(seed, 200) {
+                let events = engine.process(command);
+                for update in publisher.publish(&events) {
+                    client.apply(&update).unwrap();
+                }
+                assert_eq!(client.depth(usize::MAX), engine.book().depth(usize::MAX), "Seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_one_update_per_changed_level() {
+        let mut engine = engine(0);
+        let mut publisher = L2Publisher::new();
+        publisher.publish(&engine.process(Command::NewOrder(Order::new("1".to_string(), BidOrAsk::Ask, dec!(101), dec!(2)))));
+        publisher.publish(&engine.process(Command::NewOrder(Order::new("2".to_string(), BidOrAsk::Ask, dec!(101), dec!(3)))));
+        publisher.publish(&engine.process(Command::NewOrder(Order::new("3".to_string(), BidOrAsk::Ask, dec!(102), dec!(3)))));
+
+        let updates = publisher.publish(&engine.process(Command::NewOrder(Order::new("4".to_string(), BidOrAsk::Bid, dec!(102), dec!(6)))));
+
+        let update = |sequence, price, size, order_count| LevelUpdate { sequence, bid_or_ask: BidOrAsk::Ask, price, size, order_count };
+        assert_eq!(updates, vec![update(4, dec!(101), dec!(0), 0), update(5, dec!(102), dec!(2), 1)]);
+    }
+
+    #[test]
+    fn test_bootstrap_from_snapshot_and_buffered_updates() {
+        let mut engine = engine(4);
+        let mut publisher = L2Publisher::new();
+        let mut commands = random_commands(4, 300).into_iter();
+        for command in commands.by_ref().take(100) {
+            publisher.publish(&engine.process(command));
+        }
+
+        // The client subscribes, buffers what arrives while it fetches the snapshot, then catches up.
+        let mut buffered = vec![];
+        for command in commands.by_ref().take(20) {
+            buffered.extend(publisher.publish(&engine.process(command)));
+        }
+        let mut client = L2Book::from_snapshot(publisher.snapshot());
+        for command in commands.by_ref().take(20) {
+            buffered.extend(publisher.publish(&engine.process(command)));
+        }
+        for update in &buffered {
+            client.apply(update).unwrap();
+        }
+        for command in commands {
+            for update in publisher.publish(&engine.process(command)) {
+                client.apply(&update).unwrap();
+            }
+        }
+
+        assert_eq!(client.depth(5), engine.book().depth(5));
+        assert_eq!(client.depth(usize::MAX), engine.book().depth(usize::MAX));
+    }
+
+    #[test]
+    fn test_detects_sequence_gap() {
+        let mut engine = engine(0);
+        let mut publisher = L2Publisher::new();
+        let mut client = L2Book::from_snapshot(publisher.snapshot());
+        let first = publisher.publish(&engine.process(Command::NewOrder(Order::new("1".to_string(), BidOrAsk::Bid, dec!(99), dec!(1)))));
+        publisher.publish(&engine.process(Command::NewOrder(Order::new("2".to_string(), BidOrAsk::Bid, dec!(98), dec!(1)))));
+        let third = publisher.publish(&engine.process(Command::NewOrder(Order::new("3".to_string(), BidOrAsk::Bid, dec!(97), dec!(1)))));
+
+        client.apply(&first[0]).unwrap();
+        let result = client.apply(&third[0]);
+
+        assert_eq!(result, Err(FeedError::SequenceGap { expected: 2, actual: 3 }));
+        assert_eq!(client.sequence(), 1, "Expected the book to be left as it was");
+        assert_eq!(client.depth(usize::MAX).bids.len(), 1);
+    }
+
+    #[test]
+    fn test_publisher_for_restored_book() {
+        let mut engine = engine(0);
+        for command in random_commands(7, 100) {
+            engine.process(command);
+        }
+
+        let mut publisher = L2Publisher::from_book(engine.book());
+        let mut client = L2Book::from_snapshot(publisher.snapshot());
+        for command in random_commands(8, 100) {
+            for update in publisher.publish(&engine.process(command)) {
+                client.apply(&update).unwrap();
+            }
+        }
+
+        assert_eq!(client.depth(usize::MAX), engine.book().depth(usize::MAX));
+    }
+}
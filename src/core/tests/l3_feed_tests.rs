@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests_l3_feed {
+    use crate::core::engine::Command;
+    use crate::core::error::FeedError;
+    use crate::core::l3_feed::{L3Book, L3Event, L3Order, L3Publisher};
+    use crate::core::order::BidOrAsk;
+    use crate::core::order_book::OrderBook;
+    use crate::core::tests::support::{engine, new_order, random_commands};
+    use rust_decimal_macros::dec;
+
+    // The live book in the same shape as `L3Book::orders`
+    fn live_orders(book: &OrderBook) -> Vec<L3Order> {
+        book.ask_limits()
+            .chain(book.bid_limits())
+            .flat_map(|limit| limit.orders.iter().enumerate())
+            .map(|(position, order)| L3Order {
+                order_id: order.id.clone(),
+                bid_or_ask: order.bid_or_ask.clone(),
+                price: order.price,
+                size: order.size,
+                position,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reconstructor_matches_live_book() {
+        for seed in 0..50 {
+            let mut engine = engine(seed);
+            let mut publisher = L3Publisher::new();
+            let mut client = L3Book::new();
+
+            for command in random_commands(seed, 200) {
+                let events = engine.process(command);
+                for update in publisher.publish(&events) {
+                    client.apply(&update).unwrap();
+                }
+                assert_eq!(client.orders(), live_orders(engine.book()), "Seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_per_order() {
+        let mut engine = engine(0);
+        let mut publisher = L3Publisher::new();
+        publisher.publish(&engine.process(new_order("1", "alice", BidOrAsk::Ask, dec!(101), dec!(5))));
+        publisher.publish(&engine.process(new_order("2", "alice", BidOrAsk::Ask, dec!(101), dec!(3))));
+
+        let added = publisher.publish(&engine.process(new_order("3", "alice", BidOrAsk::Ask, dec!(101), dec!(4))));
+        let executed = publisher.publish(&engine.process(new_order("4", "alice", BidOrAsk::Bid, dec!(101), dec!(6))));
+        let reduced = publisher.publish(&engine.process(Command::Amend { order_id: "3".to_string(), price: dec!(101), size: dec!(1) }));
+        let canceled = publisher.publish(&engine.process(Command::Cancel { order_id: "3".to_string() }));
+
+        let add = |position| L3Event::Add { order_id: "3".to_string(), bid_or_ask: BidOrAsk::Ask, price: dec!(101), size: dec!(4), position };
+        assert_eq!(added.iter().map(|update| &update.event).collect::<Vec<_>>(), vec![&add(2)]);
+        assert_eq!(added[0].sequence, 3);
+        let executions: Vec<_> = executed
+            .iter()
+            .filter_map(|update| match &update.event {
+                L3Event::Execute { order_id, trade_id, size, remaining, position, .. } => {
+                    Some((order_id.as_str(), *trade_id, *size, *remaining, *position))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(executions, vec![("1", 1, dec!(5), dec!(0), 0), ("2", 2, dec!(1), dec!(2), 0)]);
+        assert!(matches!(&executed[1].event, L3Event::Delete { order_id, position: 0, .. } if order_id == "1"));
+        assert!(matches!(&reduced[..], [update] if matches!(&update.event, L3Event::Modify { size, position: 1, .. } if *size == dec!(1))));
+        assert!(matches!(&canceled[..], [update] if matches!(&update.event, L3Event::Delete { order_id, position: 1, .. } if order_id == "3")));
+    }
+
+    #[test]
+    fn test_bootstrap_and_gap_detection() {
+        let mut engine = engine(4);
+        let mut commands = random_commands(4, 300).into_iter();
+        let mut publisher = L3Publisher::new();
+        for command in commands.by_ref().take(100) {
+            publisher.publish(&engine.process(command));
+        }
+        let mut buffered = vec![];
+        for command in commands.by_ref().take(20) {
+            buffered.extend(publisher.publish(&engine.process(command)));
+        }
+        let mut client = publisher.snapshot();
+        for command in commands.by_ref().take(20) {
+            buffered.extend(publisher.publish(&engine.process(command)));
+        }
+        for update in &buffered {
+            client.apply(update).unwrap();
+        }
+        assert_eq!(client.orders(), live_orders(engine.book()));
+
+        let mut updates = vec![];
+        for command in commands {
+            updates.extend(publisher.publish(&engine.process(command)));
+        }
+        let expected = client.sequence() + 1;
+        assert_eq!(
+            client.apply(&updates[1]),
+            Err(FeedError::SequenceGap { expected, actual: expected + 1 })
+        );
+        assert_eq!(client.sequence(), expected - 1, "Expected the book to be left as it was");
+    }
+
+    #[test]
+    fn test_publisher_for_restored_book() {
+        let mut engine = engine(2);
+        for command in random_commands(7, 100) {
+            engine.process(command);
+        }
+
+        let mut publisher = L3Publisher::from_book(engine.book());
+        let mut client = publisher.snapshot();
+        assert_eq!(client.orders(), live_orders(engine.book()));
+        for command in random_commands(8, 100) {
+            for update in publisher.publish(&engine.process(command)) {
+                client.apply(&update).unwrap();
+            }
+        }
+
+        assert_eq!(client.orders(), live_orders(engine.book()));
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests_limits {
+    use crate::core::error::MatchingError;
     use crate::core::limit::Limit;
+    use crate::core::log::MatchLog;
     use crate::core::order::{BidOrAsk, Order};
     use rust_decimal::Decimal;
     use rust_decimal_macros::dec;
@@ -38,7 +40,7 @@ mod tests_limits {
         let order = create_order("1".to_string(), dec!(10), dec!(100), BidOrAsk::Ask);
         limit.add_order(order.clone(), 1);
 
-        let _done_log = limit.delete_order("1".to_string(), 1);
+        let _done_log = limit.delete_order("1".to_string(), 1).unwrap();
 
         // Assert that the order was deleted
         assert!(limit.orders.is_empty());
@@ -55,14 +57,170 @@ mod tests_limits {
         limit.add_order(order2.clone(), 1);
 
         let mut market_order = create_order("3".to_string(), dec!(12), dec!(100), BidOrAsk::Bid);
-        let match_results = limit.fill_order(&mut market_order, 1);
+        let match_results = limit.fill_order(&mut market_order, None, &mut 1, &mut 0);
 
         // Assert that the market order is filled correctly
         assert_eq!(market_order.size, dec!(0)); // Fully filled
-        assert_eq!(match_results.len(), 3); // Two match results
+        assert_eq!(match_results.logs.len(), 3); // Two match results
 
         // Assert that the orders in the limit are updated correctly
         assert_eq!(limit.orders.len(), 1); // One limit order remains
         assert_eq!(limit.orders[0].size, dec!(3)); // Remaining size is 3
+        assert_eq!(match_results.filled, vec!["1".to_string()]); // Order 1 was removed
+    }
+
+    // Test that match logs carry the executed quantities and stop once the taker is filled
+    #[test]
+    fn test_fill_order_match_logs() {
+        let mut limit = Limit::new(dec!(100));
+        limit.add_order(create_order("1".to_string(), dec!(10), dec!(100), BidOrAsk::Ask), 1);
+        limit.add_order(create_order("2".to_string(), dec!(5), dec!(100), BidOrAsk::Ask), 1);
+        limit.add_order(create_order("3".to_string(), dec!(5), dec!(100), BidOrAsk::Ask), 1);
+
+        let mut market_order = create_order("4".to_string(), dec!(12), dec!(100), BidOrAsk::Bid);
+        let mut trade_sequence = 7;
+        let match_results = limit.fill_order(&mut market_order, None, &mut 2, &mut trade_sequence);
+        let matches: Vec<&MatchLog> = match_results
+            .logs
+            .iter()
+            .filter_map(|log| log.as_any().downcast_ref::<MatchLog>())
+            .collect();
+
+        assert_eq!(matches.len(), 2); // Order 3 is never reached
+        assert_eq!(trade_sequence, 9);
+        assert_eq!(matches[0].trade_id, 8);
+        assert_eq!(matches[0].taker_order_id, "4");
+        assert_eq!(matches[0].maker_order_id, "1");
+        assert_eq!(matches[0].bid_or_ask, BidOrAsk::Bid);
+        assert_eq!(matches[0].price, dec!(100));
+        assert_eq!(matches[0].size, dec!(10));
+        assert_eq!(matches[0].taker_remaining, dec!(2));
+        assert_eq!(matches[0].maker_remaining, dec!(0));
+        assert_eq!(matches[1].trade_id, 9);
+        assert_eq!(matches[1].maker_order_id, "2");
+        assert_eq!(matches[1].size, dec!(2));
+        assert_eq!(matches[1].taker_remaining, dec!(0));
+        assert_eq!(matches[1].maker_remaining, dec!(3));
+        assert_eq!(limit.orders[1].size, dec!(5)); // Order 3 is untouched
+    }
+
+    // Test that `add_order` hands out increasing time-priority positions
+    #[test]
+    fn test_add_order_assigns_positions() {
+        let mut limit = Limit::new(dec!(100));
+        limit.add_order(create_order("1".to_string(), dec!(10), dec!(100), BidOrAsk::Ask), 1);
+        limit.add_order(create_order("2".to_string(), dec!(5), dec!(100), BidOrAsk::Ask), 2);
+
+        assert_eq!(limit.orders[0].position, 0);
+        assert_eq!(limit.orders[1].position, 1);
+        assert_eq!(limit.find_order(1).map(|order| order.id.as_str()), Some("2"));
+        assert!(limit.find_order(2).is_none());
+    }
+
+    // Test for the `cancel_order` method
+    #[test]
+    fn test_cancel_order() {
+        let mut limit = Limit::new(dec!(100));
+        limit.add_order(create_order("1".to_string(), dec!(10), dec!(100), BidOrAsk::Ask), 1);
+        limit.add_order(create_order("2".to_string(), dec!(5), dec!(100), BidOrAsk::Ask), 2);
+
+        assert!(limit.cancel_order(0, "CANCELED", 3).is_some());
+        assert!(limit.cancel_order(0, "CANCELED", 4).is_none());
+        assert_eq!(limit.orders.len(), 1);
+        assert_eq!(limit.orders[0].id, "2");
+    }
+
+    // Test that an iceberg order only shows its display size
+    #[test]
+    fn test_add_iceberg_order() {
+        let mut limit = Limit::new(dec!(100));
+        let order = create_order("1".to_string(), dec!(25), dec!(100), BidOrAsk::Ask).with_display_size(dec!(10));
+        limit.add_order(order, 1);
+
+        assert_eq!(limit.orders[0].size, dec!(10)); // Only the slice is shown
+        assert_eq!(limit.orders[0].hidden_size, dec!(15)); // The rest is held back
+        assert_eq!(limit.total_volume(), dec!(10));
+        assert_eq!(limit.available_volume(), dec!(25));
+    }
+
+    // Test that a consumed iceberg slice is refreshed at the back of the queue
+    #[test]
+    fn test_fill_iceberg_order_replenishes() {
+        let mut limit = Limit::new(dec!(100));
+        let iceberg = create_order("1".to_string(), dec!(25), dec!(100), BidOrAsk::Ask).with_display_size(dec!(10));
+        limit.add_order(iceberg, 1);
+        limit.add_order(create_order("2".to_string(), dec!(5), dec!(100), BidOrAsk::Ask), 2);
+
+        let mut market_order = create_order("3".to_string(), dec!(12), dec!(100), BidOrAsk::Bid);
+        let match_results = limit.fill_order(&mut market_order, None, &mut 3, &mut 0);
+
+        assert!(market_order.is_filled());
+        assert_eq!(limit.orders.len(), 2);
+        assert_eq!(limit.orders[0].id, "2"); // The plain order now has priority
+        assert_eq!(limit.orders[0].size, dec!(3));
+        assert_eq!(limit.orders[1].id, "1"); // The refreshed iceberg is at the back
+        assert_eq!(limit.orders[1].size, dec!(10));
+        assert_eq!(limit.orders[1].hidden_size, dec!(5));
+        assert_eq!(match_results.requeued, vec![("1".to_string(), 2)]);
+        assert!(match_results.filled.is_empty());
+    }
+
+    // Test that the last slice of an iceberg order is filled and removed
+    #[test]
+    fn test_fill_iceberg_order_exhausts_reserve() {
+        let mut limit = Limit::new(dec!(100));
+        let iceberg = create_order("1".to_string(), dec!(12), dec!(100), BidOrAsk::Ask).with_display_size(dec!(5));
+        limit.add_order(iceberg, 1);
+
+        let mut market_order = create_order("2".to_string(), dec!(20), dec!(100), BidOrAsk::Bid);
+        let match_results = limit.fill_order(&mut market_order, None, &mut 2, &mut 0);
+
+        assert_eq!(market_order.size, dec!(8));
+        assert!(limit.orders.is_empty());
+        assert_eq!(match_results.filled, vec!["1".to_string()]);
+        assert_eq!(match_results.requeued.len(), 2); // Two refreshes before the reserve ran out
+    }
+
+    // Test that `reduce_order` keeps the order in place
+    #[test]
+    fn test_reduce_order() {
+        let mut limit = Limit::new(dec!(100));
+        limit.add_order(create_order("1".to_string(), dec!(10), dec!(100), BidOrAsk::Ask), 1);
+        limit.add_order(create_order("2".to_string(), dec!(5), dec!(100), BidOrAsk::Ask), 2);
+
+        let log = limit.reduce_order(0, dec!(4), 3).expect("Expected the order to be amended");
+
+        assert!(log.keeps_priority);
+        assert_eq!(limit.orders[0].id, "1");
+        assert_eq!(limit.orders[0].size, dec!(4));
+        assert!(limit.reduce_order(7, dec!(1), 4).is_none());
+    }
+
+    // Test that `requeue_order` sends the order to the back of the queue
+    #[test]
+    fn test_requeue_order() {
+        let mut limit = Limit::new(dec!(100));
+        limit.add_order(create_order("1".to_string(), dec!(10), dec!(100), BidOrAsk::Ask), 1);
+        limit.add_order(create_order("2".to_string(), dec!(5), dec!(100), BidOrAsk::Ask), 2);
+
+        let mut order = limit.take_order(0).expect("Expected the order to be taken");
+        order.size = dec!(20);
+        let log = limit.requeue_order(order, dec!(100), dec!(10), 3);
+
+        assert!(!log.keeps_priority);
+        assert_eq!(limit.orders[0].id, "2");
+        assert_eq!(limit.orders[1].id, "1");
+        assert_eq!(limit.orders[1].position, 2);
+    }
+
+    // Test that deleting an unknown order is reported instead of panicking
+    #[test]
+    fn test_delete_unknown_order() {
+        let mut limit = Limit::new(dec!(100));
+
+        let result = limit.delete_order("1".to_string(), 1);
+
+        assert_eq!(result.err(), Some(MatchingError::UnknownOrder("1".to_string())));
+        assert_eq!(limit.total_volume(), dec!(0)); // An empty limit has no volume
     }
 }
@@ -1,2 +1,14 @@
 mod limit_tests;
 mod order_book_tests;
+mod trigger_book_tests;
+mod snapshot_tests;
+mod instrument_tests;
+mod engine_tests;
+mod snapshot_store_tests;
+mod journal_tests;
+mod recovery_tests;
+mod exchange_tests;
+mod l2_feed_tests;
+mod l3_feed_tests;
+mod candles_tests;
+mod support;
@@ -1,7 +1,14 @@
+#[cfg(test)]
 mod tests_order_book {
+    use rust_decimal::Decimal;
     use rust_decimal_macros::dec;
-    use crate::core::order::{Order, BidOrAsk};
+    use crate::core::order::{Order, BidOrAsk, OrderType, PostOnly, SelfTradePrevention, TimeInForce};
+    use std::time::{Duration, SystemTime};
+    use crate::core::error::MatchingError;
+    use crate::core::instrument::InstrumentSpec;
+    use crate::core::log::{AmendLog, DoneLog, MatchLog, RejectLog, RejectReason};
     use crate::core::order_book::OrderBook;
+    use crate::core::depth::PriceLevel;
 
     #[test]
     fn test_new_order_book() {
@@ -17,7 +24,7 @@ mod tests_order_book {
         let price = dec!(100.0);
         let  order = Order::new("1".to_string(),  BidOrAsk::Bid, price,dec!(10), );
 
-        let log = order_book.add_limit_order(price, order);
+        let log = order_book.add_limit_order(price, order).unwrap();
 
         assert_eq!(order_book.bids.len(), 1, "Expected one bid limit");
         assert!(order_book.bids.contains_key(&price), "Expected bid price level to exist");
@@ -30,7 +37,7 @@ mod tests_order_book {
         let price = dec!(200.0);
         let order = Order::new("2".to_string(),BidOrAsk::Ask,price,  dec!(5.0), );
 
-        let log = order_book.add_limit_order(price, order);
+        let log = order_book.add_limit_order(price, order).unwrap();
 
         assert_eq!(order_book.asks.len(), 1, "Expected one ask limit");
         assert!(order_book.asks.contains_key(&price), "Expected ask price level to exist");
@@ -42,10 +49,10 @@ mod tests_order_book {
         let mut order_book = OrderBook::new();
         let price = dec!(100.0);
         let limit_order = Order::new("1".to_string(), BidOrAsk::Ask,price,  dec!(10.0), );
-        order_book.add_limit_order(price, limit_order);
+        order_book.add_limit_order(price, limit_order).unwrap();
 
         let mut market_order = Order::new("2".to_string(),BidOrAsk::Bid,price, dec!(5.0),  );
-        let logs = order_book.fill_market_order(&mut market_order);
+        let logs = order_book.fill_market_order(&mut market_order).unwrap();
 
         assert_eq!(logs.len(), 1, "Expected one log for a partial fill");
         assert!(market_order.is_filled(), "Expected market order to be fully filled");
@@ -57,10 +64,10 @@ mod tests_order_book {
         let mut order_book = OrderBook::new();
         let price = dec!(150.0);
         let limit_order = Order::new("1".to_string(), BidOrAsk::Bid,price, dec!(20.0));
-        order_book.add_limit_order(price, limit_order);
+        order_book.add_limit_order(price, limit_order).unwrap();
 
         let mut market_order = Order::new("2".to_string(),  BidOrAsk::Ask, price,dec!(15.0));
-        let logs = order_book.fill_market_order(&mut market_order);
+        let logs = order_book.fill_market_order(&mut market_order).unwrap();
 
         assert_eq!(logs.len(), 1, "Expected one log for a partial fill");
         assert!(market_order.is_filled(), "Expected market order to be fully filled");
@@ -73,13 +80,13 @@ mod tests_order_book {
         let price = dec!(120.0);
         let limit_order1 = Order::new("1".to_string(), BidOrAsk::Ask, dec!(10.0), price);
         let limit_order2 = Order::new("2".to_string(),  BidOrAsk::Ask,dec!(10.0), price);
-        order_book.add_limit_order(price, limit_order1);
-        order_book.add_limit_order(price, limit_order2);
+        order_book.add_limit_order(price, limit_order1).unwrap();
+        order_book.add_limit_order(price, limit_order2).unwrap();
 
         let mut market_order = Order::new("3".to_string(), BidOrAsk::Bid,dec!(15.0), price, );
-        let logs = order_book.fill_market_order(&mut market_order);
+        let logs = order_book.fill_market_order(&mut market_order).unwrap();
 
-        assert_eq!(logs.len(), 3, "Expected two logs for partial fills");
+        assert_eq!(logs.len(), 2, "Expected a match and a done log");
         assert!(market_order.is_filled(), "Expected market order to be fully filled");
         assert_eq!(order_book.asks[&price].orders.len(), 1, "Expected one remaining limit order");
     }
@@ -90,14 +97,749 @@ mod tests_order_book {
         let price = dec!(110.0);
         let limit_order1 = Order::new("1".to_string(),  BidOrAsk::Ask,dec!(10.0), price,);
         let limit_order2 = Order::new("2".to_string(),  BidOrAsk::Ask, dec!(10.0), price,);
-        order_book.add_limit_order(price, limit_order1);
-        order_book.add_limit_order(price, limit_order2);
+        order_book.add_limit_order(price, limit_order1).unwrap();
+        order_book.add_limit_order(price, limit_order2).unwrap();
 
         let mut market_order = Order::new("3".to_string(), BidOrAsk::Bid,dec!(20.0), price, );
-        let logs = order_book.fill_market_order(&mut market_order);
+        let logs = order_book.fill_market_order(&mut market_order).unwrap();
 
-        assert_eq!(logs.len(), 3, "Expected two logs for complete fills");
+        assert_eq!(logs.len(), 2, "Expected a match and a done log");
         assert!(market_order.is_filled(), "Expected market order to be fully filled");
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_fill_market_order_sweeps_multiple_levels() {
+        let mut order_book = OrderBook::new();
+        order_book.add_limit_order(dec!(100), Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.add_limit_order(dec!(101), Order::new("2".to_string(), BidOrAsk::Ask, dec!(101), dec!(5))).unwrap();
+        order_book.add_limit_order(dec!(102), Order::new("3".to_string(), BidOrAsk::Ask, dec!(102), dec!(5))).unwrap();
+
+        let mut market_order = Order::new("4".to_string(), BidOrAsk::Bid, dec!(102), dec!(12));
+        order_book.fill_market_order(&mut market_order).unwrap();
+
+        assert!(market_order.is_filled(), "Expected market order to be fully filled");
+        assert!(!order_book.asks.contains_key(&dec!(100)), "Expected first level to be consumed");
+        assert!(!order_book.asks.contains_key(&dec!(101)), "Expected second level to be consumed");
+        assert_eq!(order_book.asks[&dec!(102)].orders[0].size, dec!(3), "Expected third level to be reduced");
+
+        let asks = order_book.ask_limits().collect::<Vec<_>>();
+        assert_eq!(asks.len(), 1, "Expected one ask level to remain");
+        assert_eq!(asks[0].price, dec!(102));
+    }
+
+    #[test]
+    fn test_match_logs_across_levels() {
+        let mut order_book = OrderBook::new();
+        order_book.add_limit_order(dec!(100), Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.add_limit_order(dec!(101), Order::new("2".to_string(), BidOrAsk::Ask, dec!(101), dec!(5))).unwrap();
+
+        let mut market_order = Order::new("3".to_string(), BidOrAsk::Bid, dec!(0), dec!(7));
+        let logs = order_book.fill_market_order(&mut market_order).unwrap();
+        let matches: Vec<(i64, Decimal, Decimal, Decimal)> = logs
+            .iter()
+            .filter_map(|log| log.as_any().downcast_ref::<MatchLog>())
+            .map(|log| (log.trade_id, log.price, log.size, log.taker_remaining))
+            .collect();
+
+        assert_eq!(matches, vec![(1, dec!(100), dec!(5), dec!(2)), (2, dec!(101), dec!(2), dec!(0))]);
+    }
+
+    #[test]
+    fn test_fill_market_order_does_not_reuse_liquidity() {
+        let mut order_book = OrderBook::new();
+        order_book.add_limit_order(dec!(150), Order::new("1".to_string(), BidOrAsk::Bid, dec!(150), dec!(10))).unwrap();
+
+        let mut first = Order::new("2".to_string(), BidOrAsk::Ask, dec!(150), dec!(10));
+        let logs = order_book.fill_market_order(&mut first).unwrap();
+        assert_eq!(logs.len(), 2, "Expected a match log and a done log");
+        assert!(first.is_filled(), "Expected first market order to be fully filled");
+        assert!(order_book.bids.is_empty(), "Expected the bid level to be removed");
+
+        let mut second = Order::new("3".to_string(), BidOrAsk::Ask, dec!(150), dec!(10));
+        let logs = order_book.fill_market_order(&mut second).unwrap();
+        assert_eq!(logs.len(), 1, "Expected only a done log against an empty book");
+        let done = logs[0].as_any().downcast_ref::<DoneLog>().expect("Expected the remainder to be canceled");
+        assert_eq!((done.order_id.as_str(), done.remaining_size, done.reason.as_str()), ("3", dec!(10), "CANCELED"));
+        assert_eq!(second.size, dec!(10), "Expected second market order to remain unfilled");
+    }
+
+    #[test]
+    fn test_limits_iterate_from_top_of_book() {
+        let mut order_book = OrderBook::new();
+        for (id, price) in [("1", dec!(101)), ("2", dec!(99)), ("3", dec!(100))] {
+            order_book.add_limit_order(price, Order::new(id.to_string(), BidOrAsk::Ask, price, dec!(1))).unwrap();
+            order_book.add_limit_order(price - dec!(10), Order::new(format!("b{id}"), BidOrAsk::Bid, price - dec!(10), dec!(1))).unwrap();
+        }
+
+        let ask_prices = order_book.ask_limits().map(|limit| limit.price).collect::<Vec<_>>();
+        let bid_prices = order_book.bid_limits().map(|limit| limit.price).collect::<Vec<_>>();
+
+        assert_eq!(ask_prices, vec![dec!(99), dec!(100), dec!(101)], "Expected asks cheapest first");
+        assert_eq!(bid_prices, vec![dec!(91), dec!(90), dec!(89)], "Expected bids highest first");
+        assert_eq!(order_book.best_ask().map(|limit| limit.price), Some(dec!(99)));
+        assert_eq!(order_book.best_bid().map(|limit| limit.price), Some(dec!(91)));
+    }
+
+    #[test]
+    fn test_get_order() {
+        let mut order_book = OrderBook::new();
+        order_book.add_limit_order(dec!(100), Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))).unwrap();
+
+        let order = order_book.get_order("1").expect("Expected order to be resting");
+        assert_eq!(order.size, dec!(10));
+        assert!(order_book.get_order("2").is_none(), "Expected unknown order to be missing");
+    }
+
+    #[test]
+    fn test_cancel_order() {
+        let mut order_book = OrderBook::new();
+        order_book.add_limit_order(dec!(100), Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))).unwrap();
+        order_book.add_limit_order(dec!(100), Order::new("2".to_string(), BidOrAsk::Bid, dec!(100), dec!(5))).unwrap();
+        order_book.add_limit_order(dec!(99), Order::new("3".to_string(), BidOrAsk::Bid, dec!(99), dec!(5))).unwrap();
+
+        assert!(order_book.cancel_order("1").is_ok(), "Expected resting order to be canceled");
+        assert!(order_book.get_order("1").is_none(), "Expected canceled order to be gone");
+        assert_eq!(order_book.bids[&dec!(100)].orders.len(), 1, "Expected one order to remain at the level");
+
+        assert!(order_book.cancel_order("3").is_ok(), "Expected resting order to be canceled");
+        assert!(!order_book.bids.contains_key(&dec!(99)), "Expected empty level to be removed");
+        assert_eq!(order_book.get_order("2").map(|order| order.size), Some(dec!(5)));
+    }
+
+    #[test]
+    fn test_cancel_unknown_order() {
+        let mut order_book = OrderBook::new();
+
+        let result = order_book.cancel_order("1");
+
+        assert_eq!(result.err(), Some(MatchingError::UnknownOrder("1".to_string())));
+    }
+
+    #[test]
+    fn test_cancel_filled_order() {
+        let mut order_book = OrderBook::new();
+        order_book.add_limit_order(dec!(100), Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(10))).unwrap();
+
+        let mut market_order = Order::new("2".to_string(), BidOrAsk::Bid, dec!(100), dec!(10));
+        order_book.fill_market_order(&mut market_order).unwrap();
+
+        assert!(order_book.get_order("1").is_none(), "Expected filled order to be gone");
+        assert!(order_book.cancel_order("1").is_err(), "Expected filled order not to be cancelable");
+    }
+
+    #[test]
+    fn test_place_limit_order_rests_when_not_crossing() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(10))).unwrap();
+
+        let logs = order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(99), dec!(10))).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected only an open log");
+        assert_eq!(order_book.best_bid().map(|limit| limit.price), Some(dec!(99)));
+        assert_eq!(order_book.best_ask().map(|limit| limit.price), Some(dec!(100)));
+    }
+
+    #[test]
+    fn test_place_limit_order_crosses_up_to_limit_price() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(105), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Ask, dec!(106), dec!(5))).unwrap();
+
+        let logs = order_book.place_limit_order(Order::new("4".to_string(), BidOrAsk::Bid, dec!(105), dec!(12))).unwrap();
+
+        assert_eq!(logs.len(), 5, "Expected two matches, two done logs and an open log");
+        assert_eq!(order_book.best_ask().map(|limit| limit.price), Some(dec!(106)));
+        assert_eq!(order_book.best_bid().map(|limit| limit.price), Some(dec!(105)));
+        assert_eq!(order_book.get_order("4").map(|order| order.size), Some(dec!(2)), "Expected the remainder to rest");
+        assert!(order_book.get_order("1").is_none(), "Expected crossed ask to be filled");
+        assert!(order_book.get_order("2").is_none(), "Expected crossed ask to be filled");
+    }
+
+    #[test]
+    fn test_place_limit_order_fully_filled_does_not_rest() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))).unwrap();
+
+        let logs = order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(95), dec!(4))).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected a single match log");
+        assert!(order_book.asks.is_empty(), "Expected the fully filled ask not to rest");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(6)));
+    }
+
+    #[test]
+    fn test_immediate_or_cancel_drops_remainder() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+
+        let order = Order::new("2".to_string(), BidOrAsk::Bid, dec!(100), dec!(8))
+            .with_time_in_force(TimeInForce::ImmediateOrCancel);
+        let logs = order_book.place_limit_order(order).unwrap();
+
+        assert_eq!(logs.len(), 3, "Expected a match, a done log for the maker and a cancel for the remainder");
+        assert!(order_book.get_order("2").is_none(), "Expected the remainder not to rest");
+        assert!(order_book.bids.is_empty() && order_book.asks.is_empty(), "Expected an empty book");
+    }
+
+    #[test]
+    fn test_fill_or_kill_rejects_without_trading() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(102), dec!(5))).unwrap();
+
+        let order = Order::new("3".to_string(), BidOrAsk::Bid, dec!(101), dec!(8))
+            .with_time_in_force(TimeInForce::FillOrKill);
+        let logs = order_book.place_limit_order(order).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected a single done log");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected the book to be untouched");
+        assert!(order_book.get_order("3").is_none(), "Expected the killed order not to rest");
+
+        let mut market_order = Order::new("4".to_string(), BidOrAsk::Bid, dec!(0), dec!(11))
+            .with_time_in_force(TimeInForce::FillOrKill);
+        order_book.fill_market_order(&mut market_order).unwrap();
+        assert_eq!(market_order.size, dec!(11), "Expected the killed market order not to trade");
+    }
+
+    #[test]
+    fn test_fill_or_kill_fills_across_levels() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(99), dec!(5))).unwrap();
+
+        let order = Order::new("3".to_string(), BidOrAsk::Ask, dec!(99), dec!(8))
+            .with_time_in_force(TimeInForce::FillOrKill);
+        order_book.place_limit_order(order).unwrap();
+
+        assert!(order_book.get_order("1").is_none(), "Expected the best bid to be filled");
+        assert_eq!(order_book.get_order("2").map(|order| order.size), Some(dec!(2)));
+        assert!(order_book.asks.is_empty(), "Expected nothing to rest");
+    }
+
+    #[test]
+    fn test_expire_orders() {
+        let mut order_book = OrderBook::new();
+        let now = SystemTime::now();
+        let soon = now + Duration::from_secs(60);
+        let later = now + Duration::from_secs(120);
+        order_book.place_limit_order(
+            Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(5)).with_time_in_force(TimeInForce::GoodTillDate(soon)),
+        ).unwrap();
+        order_book.place_limit_order(
+            Order::new("2".to_string(), BidOrAsk::Bid, dec!(99), dec!(5)).with_time_in_force(TimeInForce::GoodTillDate(later)),
+        ).unwrap();
+        order_book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(98), dec!(5))).unwrap();
+
+        assert!(order_book.expire_orders(now).is_empty(), "Expected nothing to expire yet");
+
+        let logs = order_book.expire_orders(soon);
+        assert_eq!(logs.len(), 1, "Expected one order to expire");
+        assert!(order_book.get_order("1").is_none(), "Expected the expired order to be removed");
+        assert!(!order_book.bids.contains_key(&dec!(100)), "Expected the empty level to be removed");
+
+        order_book.cancel_order("2").unwrap();
+        assert!(order_book.expire_orders(later).is_empty(), "Expected canceled orders not to expire");
+        assert!(order_book.get_order("3").is_some(), "Expected good-till-cancel orders to remain");
+    }
+
+    #[test]
+    fn test_post_only_rests_when_not_crossing() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+
+        let order = Order::new("2".to_string(), BidOrAsk::Bid, dec!(99.99), dec!(5)).with_post_only(PostOnly::Reject);
+        let logs = order_book.place_limit_order(order).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected only an open log");
+        assert_eq!(order_book.get_order("2").map(|order| order.price), Some(dec!(99.99)));
+    }
+
+    #[test]
+    fn test_post_only_reject_does_not_trade() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+
+        let order = Order::new("2".to_string(), BidOrAsk::Bid, dec!(101), dec!(5)).with_post_only(PostOnly::Reject);
+        let logs = order_book.place_limit_order(order).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected a single reject log");
+        assert!(order_book.get_order("2").is_none(), "Expected the rejected order not to rest");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected the ask to be untouched");
+    }
+
+    #[test]
+    fn test_post_only_slide_reprices_one_tick_away() {
+        let mut order_book = OrderBook::with_spec(InstrumentSpec {
+            tick_size: dec!(0.5),
+            ..InstrumentSpec::default()
+        });
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(5))).unwrap();
+
+        let order = Order::new("2".to_string(), BidOrAsk::Ask, dec!(99), dec!(5)).with_post_only(PostOnly::Slide);
+        let logs = order_book.place_limit_order(order).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected only an open log");
+        assert_eq!(order_book.get_order("2").map(|order| order.price), Some(dec!(100.5)));
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected the bid to be untouched");
+    }
+
+    #[test]
+    fn test_post_only_slide_rejects_invalid_price() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(0.01), dec!(5))).unwrap();
+
+        let order = Order::new("2".to_string(), BidOrAsk::Bid, dec!(0.05), dec!(5)).with_post_only(PostOnly::Slide);
+        let logs = order_book.place_limit_order(order).unwrap();
+
+        let reject = logs[0].as_any().downcast_ref::<RejectLog>().expect("Expected a reject log");
+        assert_eq!((logs.len(), &reject.reason), (1, &RejectReason::PostOnlyWouldTake));
+        assert!(order_book.get_order("2").is_none(), "Expected no order to rest at a price of zero");
+
+        let mut order_book = OrderBook::with_spec(InstrumentSpec {
+            min_notional: Some(dec!(500)),
+            ..InstrumentSpec::default()
+        });
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+
+        let order = Order::new("2".to_string(), BidOrAsk::Bid, dec!(101), dec!(5)).with_post_only(PostOnly::Slide);
+        let logs = order_book.place_limit_order(order).unwrap();
+
+        let reject = logs[0].as_any().downcast_ref::<RejectLog>().expect("Expected a reject log");
+        assert_eq!((logs.len(), &reject.reason), (1, &RejectReason::NotionalBelowMinimum));
+        assert!(order_book.get_order("2").is_none(), "Expected the slid order to break the minimum notional");
+    }
+
+    #[test]
+    fn test_stop_order_waits_for_trigger() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(105), dec!(5))).unwrap();
+
+        let stop = Order::new("3".to_string(), BidOrAsk::Bid, dec!(0), dec!(3)).with_order_type(OrderType::StopMarket(dec!(100)));
+        let logs = order_book.place_order(stop).unwrap();
+
+        assert!(logs.is_empty(), "Expected the stop to wait without a last trade price");
+        assert!(order_book.get_order("3").is_some(), "Expected the stop to be pending");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected no trade yet");
+
+        let logs = order_book.place_order(Order::new("4".to_string(), BidOrAsk::Bid, dec!(100), dec!(1))).unwrap();
+
+        assert_eq!(logs.len(), 3, "Expected the trade, the trigger and the stop's trade");
+        assert!(order_book.get_order("3").is_none(), "Expected the stop to have executed");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(1)));
+    }
+
+    #[test]
+    fn test_stop_orders_cascade() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(1))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(98), dec!(1))).unwrap();
+        order_book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(95), dec!(1))).unwrap();
+
+        order_book.place_order(
+            Order::new("s1".to_string(), BidOrAsk::Ask, dec!(0), dec!(1)).with_order_type(OrderType::StopMarket(dec!(100))),
+        ).unwrap();
+        order_book.place_order(
+            Order::new("s2".to_string(), BidOrAsk::Ask, dec!(0), dec!(1)).with_order_type(OrderType::StopMarket(dec!(98))),
+        ).unwrap();
+
+        let logs = order_book.place_order(
+            Order::new("4".to_string(), BidOrAsk::Ask, dec!(0), dec!(1)).with_order_type(OrderType::Market),
+        ).unwrap();
+
+        assert_eq!(logs.len(), 8, "Expected three trades, each with a done log, and two triggers");
+        assert!(order_book.bids.is_empty(), "Expected the cascade to consume every bid");
+        assert!(order_book.get_order("s1").is_none() && order_book.get_order("s2").is_none());
+    }
+
+    #[test]
+    fn test_stop_limit_rests_after_trigger() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(1))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(110), dec!(1))).unwrap();
+        order_book.place_order(
+            Order::new("3".to_string(), BidOrAsk::Bid, dec!(101), dec!(2)).with_order_type(OrderType::StopLimit(dec!(100))),
+        ).unwrap();
+
+        order_book.place_order(Order::new("4".to_string(), BidOrAsk::Bid, dec!(100), dec!(1))).unwrap();
+
+        let order = order_book.get_order("3").expect("Expected the stop-limit to rest");
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order_book.best_bid().map(|limit| limit.price), Some(dec!(101)));
+    }
+
+    #[test]
+    fn test_cancel_pending_stop_order() {
+        let mut order_book = OrderBook::new();
+        order_book.place_order(
+            Order::new("1".to_string(), BidOrAsk::Ask, dec!(0), dec!(1)).with_order_type(OrderType::StopMarket(dec!(90))),
+        ).unwrap();
+
+        assert!(order_book.cancel_order("1").is_ok(), "Expected the pending stop to be canceled");
+        assert!(order_book.get_order("1").is_none());
+
+        let logs = order_book.place_stop_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(90), dec!(1))).unwrap();
+        assert_eq!(logs.len(), 1, "Expected a reject log for an order without a stop price");
+    }
+
+    #[test]
+    fn test_iceberg_order_hides_size() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(
+            Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(30)).with_display_size(dec!(10)),
+        ).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+
+        assert_eq!(order_book.best_ask().map(|limit| limit.total_volume()), Some(dec!(15)), "Expected only the slice to be visible");
+
+        order_book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(12))).unwrap();
+        assert!(order_book.get_order("3").is_none(), "Expected the bid to be filled");
+
+        let iceberg = order_book.get_order("1").expect("Expected the iceberg to keep resting");
+        assert_eq!(iceberg.size, dec!(10));
+        assert_eq!(iceberg.total_size(), dec!(20));
+        assert_eq!(order_book.best_ask().unwrap().orders[0].id, "2", "Expected the refreshed iceberg to lose priority");
+        assert!(order_book.cancel_order("1").is_ok(), "Expected the requeued iceberg to stay cancelable");
+    }
+
+    #[test]
+    fn test_fill_or_kill_counts_hidden_size() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(
+            Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(30)).with_display_size(dec!(5)),
+        ).unwrap();
+
+        let order = Order::new("2".to_string(), BidOrAsk::Ask, dec!(100), dec!(20))
+            .with_time_in_force(TimeInForce::FillOrKill);
+        order_book.place_limit_order(order).unwrap();
+
+        assert_eq!(order_book.get_order("1").map(|order| order.total_size()), Some(dec!(10)));
+    }
+
+    #[test]
+    fn test_amend_order_size_reduction_keeps_priority() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))).unwrap();
+
+        let logs = order_book.amend_order("1", dec!(100), dec!(4)).expect("Expected the amend to succeed");
+
+        assert_eq!(logs.len(), 1, "Expected a single amend log");
+        let limit = order_book.best_bid().unwrap();
+        assert_eq!(limit.orders[0].id, "1", "Expected the order to keep its place");
+        assert_eq!(limit.orders[0].size, dec!(4));
+    }
+
+    #[test]
+    fn test_amend_order_size_increase_loses_priority() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))).unwrap();
+
+        order_book.amend_order("1", dec!(100), dec!(15)).expect("Expected the amend to succeed");
+
+        let limit = order_book.best_bid().unwrap();
+        assert_eq!(limit.orders[0].id, "2", "Expected the other order to take priority");
+        assert_eq!(limit.orders[1].id, "1");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(15)));
+    }
+
+    #[test]
+    fn test_amend_order_price_change_moves_level() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(105), dec!(10))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(104), dec!(10))).unwrap();
+
+        order_book.amend_order("1", dec!(104), dec!(3)).expect("Expected the amend to succeed");
+
+        assert!(!order_book.asks.contains_key(&dec!(105)), "Expected the old level to be removed");
+        let limit = order_book.best_ask().unwrap();
+        assert_eq!(limit.orders[1].id, "1", "Expected the order to queue behind the existing one");
+        assert!(order_book.cancel_order("1").is_ok(), "Expected the moved order to stay cancelable");
+    }
+
+    #[test]
+    fn test_amend_order_crossing_replaces() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(98), dec!(8))).unwrap();
+
+        let logs = order_book.amend_order("2", dec!(100), dec!(8)).expect("Expected the amend to succeed");
+
+        assert_eq!(logs.len(), 4, "Expected a replace, a match, a done log and an open log");
+        assert!(order_book.asks.is_empty(), "Expected the ask to be filled");
+        assert_eq!(order_book.get_order("2").map(|order| (order.price, order.size)), Some((dec!(100), dec!(3))));
+    }
+
+    #[test]
+    fn test_amend_order_errors() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+
+        assert_eq!(order_book.amend_order("2", dec!(100), dec!(5)).err(), Some(MatchingError::UnknownOrder("2".to_string())));
+        assert_eq!(order_book.amend_order("1", dec!(0), dec!(5)).err(), Some(MatchingError::InvalidPrice(dec!(0))));
+        assert_eq!(order_book.amend_order("1", dec!(100), dec!(-1)).err(), Some(MatchingError::InvalidSize(dec!(-1))));
+    }
+
+    // Helper to build a book with a resting ask from "firm-a" followed by one from "firm-b"
+    fn self_trade_book(mode: SelfTradePrevention) -> OrderBook {
+        let mut order_book = OrderBook::new();
+        order_book.set_self_trade_prevention(Some(mode));
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5)).with_owner("firm-a".to_string())).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(100), dec!(5)).with_owner("firm-b".to_string())).unwrap();
+        order_book
+    }
+
+    #[test]
+    fn test_self_trade_cancel_newest() {
+        let mut order_book = self_trade_book(SelfTradePrevention::CancelNewest);
+
+        let logs = order_book.place_limit_order(
+            Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(8)).with_owner("firm-a".to_string()),
+        ).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected a single STP done log");
+        assert!(order_book.get_order("3").is_none(), "Expected the incoming order to be canceled");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected the resting order to be untouched");
+    }
+
+    #[test]
+    fn test_self_trade_cancel_oldest() {
+        let mut order_book = self_trade_book(SelfTradePrevention::CancelOldest);
+
+        let logs = order_book.place_limit_order(
+            Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(8)).with_owner("firm-a".to_string()),
+        ).unwrap();
+
+        assert_eq!(logs.len(), 4, "Expected an STP done log, a match, a done log and an open log");
+        assert!(order_book.get_order("1").is_none(), "Expected the resting order to be canceled");
+        assert!(order_book.get_order("2").is_none(), "Expected the other firm's order to trade");
+        assert_eq!(order_book.get_order("3").map(|order| order.size), Some(dec!(3)));
+    }
+
+    #[test]
+    fn test_self_trade_cancel_both() {
+        let mut order_book = self_trade_book(SelfTradePrevention::CancelBoth);
+
+        let logs = order_book.place_limit_order(
+            Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(8)).with_owner("firm-a".to_string()),
+        ).unwrap();
+
+        assert_eq!(logs.len(), 2, "Expected an STP done log for each order");
+        assert!(order_book.get_order("1").is_none() && order_book.get_order("3").is_none());
+        assert_eq!(order_book.get_order("2").map(|order| order.size), Some(dec!(5)));
+    }
+
+    #[test]
+    fn test_self_trade_decrement_and_cancel() {
+        let mut order_book = self_trade_book(SelfTradePrevention::DecrementAndCancel);
+
+        let logs = order_book.place_limit_order(
+            Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(3)).with_owner("firm-a".to_string()),
+        ).unwrap();
+
+        assert_eq!(logs.len(), 2, "Expected an amend log for the resting order and an STP done log for the incoming order");
+        let amend = logs[0].as_any().downcast_ref::<AmendLog>().expect("Expected the decrement to be reported");
+        assert_eq!((amend.order_id.as_str(), amend.old_size, amend.size, amend.keeps_priority), ("1", dec!(5), dec!(2), true));
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(2)), "Expected the resting order to be decremented");
+        assert!(order_book.get_order("3").is_none());
+    }
+
+    #[test]
+    fn test_fill_or_kill_ignores_own_liquidity_under_self_trade_prevention() {
+        let modes = [
+            SelfTradePrevention::CancelNewest,
+            SelfTradePrevention::CancelOldest,
+            SelfTradePrevention::CancelBoth,
+            SelfTradePrevention::DecrementAndCancel,
+        ];
+        for mode in modes {
+            let mut order_book = self_trade_book(mode.clone());
+
+            let logs = order_book.place_limit_order(
+                Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(10))
+                    .with_owner("firm-a".to_string())
+                    .with_time_in_force(TimeInForce::FillOrKill),
+            ).unwrap();
+
+            assert_eq!(logs.len(), 1, "Expected a single done log under {:?}", mode);
+            assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected {:?} to leave the own order", mode);
+            assert_eq!(order_book.get_order("2").map(|order| order.size), Some(dec!(5)), "Expected {:?} not to trade", mode);
+
+            let mut market_order = Order::new("4".to_string(), BidOrAsk::Bid, dec!(0), dec!(6))
+                .with_owner("firm-a".to_string())
+                .with_time_in_force(TimeInForce::FillOrKill);
+            order_book.fill_market_order(&mut market_order).unwrap();
+            assert_eq!(market_order.size, dec!(6), "Expected the killed market order not to trade under {:?}", mode);
+        }
+
+        let mut order_book = self_trade_book(SelfTradePrevention::CancelOldest);
+        order_book.place_limit_order(
+            Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(5))
+                .with_owner("firm-a".to_string())
+                .with_time_in_force(TimeInForce::FillOrKill),
+        ).unwrap();
+        assert!(order_book.get_order("2").is_none(), "Expected the other firm's liquidity to fill the order");
+    }
+
+    #[test]
+    fn test_self_trade_allowed_without_mode() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5)).with_owner("firm-a".to_string())).unwrap();
+
+        order_book.place_limit_order(
+            Order::new("2".to_string(), BidOrAsk::Bid, dec!(100), dec!(5)).with_owner("firm-a".to_string()),
+        ).unwrap();
+
+        assert!(order_book.asks.is_empty() && order_book.bids.is_empty(), "Expected the orders to trade");
+    }
+
+    #[test]
+    fn test_rejects_invalid_orders() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(5))).unwrap();
+
+        let duplicate = order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(99), dec!(5)));
+        let zero_size = order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(99), dec!(0)));
+        let negative_price = order_book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(-1), dec!(5)));
+        let bad_level = order_book.add_limit_order(dec!(0), Order::new("4".to_string(), BidOrAsk::Ask, dec!(0), dec!(5)));
+        let bad_stop = order_book.place_order(
+            Order::new("5".to_string(), BidOrAsk::Ask, dec!(0), dec!(5)).with_order_type(OrderType::StopMarket(dec!(0))),
+        );
+
+        assert_eq!(duplicate.err(), Some(MatchingError::DuplicateOrderId("1".to_string())));
+        assert_eq!(zero_size.err(), Some(MatchingError::InvalidSize(dec!(0))));
+        assert_eq!(negative_price.err(), Some(MatchingError::InvalidPrice(dec!(-1))));
+        assert_eq!(bad_level.err(), Some(MatchingError::InvalidPrice(dec!(0))));
+        assert_eq!(bad_stop.err(), Some(MatchingError::InvalidPrice(dec!(0))));
+        assert_eq!(order_book.bids.len(), 1, "Expected refused orders to leave the book untouched");
+    }
+
+    #[test]
+    fn test_halted_book_refuses_new_orders() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(5))).unwrap();
+        order_book.halt();
+
+        let placed = order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(100), dec!(5)));
+        let amended = order_book.amend_order("1", dec!(101), dec!(5));
+
+        assert!(order_book.is_halted());
+        assert_eq!(placed.err(), Some(MatchingError::BookHalted));
+        assert_eq!(amended.err(), Some(MatchingError::BookHalted));
+        assert!(order_book.cancel_order("1").is_ok(), "Expected cancels to be processed while halted");
+
+        order_book.resume();
+        assert!(order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_orders_breaking_the_spec() {
+        let mut order_book = OrderBook::with_spec(InstrumentSpec {
+            tick_size: dec!(0.5),
+            lot_size: Some(dec!(1)),
+            ..InstrumentSpec::default()
+        });
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+
+        let off_tick = order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(100.2), dec!(5))).unwrap();
+        let off_lot = order_book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(1.5))).unwrap();
+        let mut market_order = Order::new("4".to_string(), BidOrAsk::Bid, dec!(0), dec!(0.5));
+        let market = order_book.fill_market_order(&mut market_order).unwrap();
+        let resting = order_book.add_limit_order(dec!(99.9), Order::new("5".to_string(), BidOrAsk::Bid, dec!(99.9), dec!(1)));
+
+        assert_eq!(off_tick.len(), 1, "Expected a single reject log");
+        assert_eq!(off_lot.len(), 1, "Expected a single reject log");
+        assert_eq!(market.len(), 1, "Expected a single reject log");
+        assert_eq!(resting.err(), Some(MatchingError::Rejected(RejectReason::PriceNotOnTick)));
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected no trades");
+        assert!(order_book.bids.is_empty(), "Expected nothing to rest");
+    }
+
+    #[test]
+    fn test_rejects_amendment_breaking_the_spec() {
+        let mut order_book = OrderBook::with_spec(InstrumentSpec {
+            min_size: Some(dec!(2)),
+            ..InstrumentSpec::default()
+        });
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(100), dec!(5))).unwrap();
+
+        let logs = order_book.amend_order("1", dec!(100), dec!(1)).unwrap();
+
+        assert_eq!(logs.len(), 1, "Expected a single reject log");
+        assert_eq!(order_book.get_order("1").map(|order| order.size), Some(dec!(5)), "Expected the order to be unchanged");
+    }
+
+    #[test]
+    fn test_every_log_has_its_own_sequence() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Ask, dec!(101), dec!(5))).unwrap();
+
+        let logs = order_book.place_limit_order(Order::new("4".to_string(), BidOrAsk::Bid, dec!(101), dec!(20))).unwrap();
+        let sequences: Vec<i64> = logs.iter().map(|log| log.get_seq()).collect();
+
+        assert_eq!(sequences, (4..=10).collect::<Vec<i64>>(), "Expected three matches, three fills and an open");
+    }
+
+    #[test]
+    fn test_restore_continues_sequences() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(100), dec!(5))).unwrap();
+        order_book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Bid, dec!(100), dec!(2))).unwrap();
+        let snapshot = order_book.snapshot("BTC/USD".to_string()).unwrap();
+        assert_eq!((snapshot.log_seq(), snapshot.trade_seq()), (2, 1));
+
+        let mut restored = OrderBook::new();
+        restored.restore(snapshot).unwrap();
+        let logs = restored.place_limit_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(100), dec!(1))).unwrap();
+        let trade = logs[0].as_any().downcast_ref::<MatchLog>().expect("Expected a match log");
+
+        assert_eq!(logs[0].get_seq(), 3, "Expected the log sequence to continue");
+        assert_eq!(trade.trade_id, 2, "Expected the trade sequence to continue");
+        assert_eq!(restored.get_order("1").map(|order| order.size), Some(dec!(2)));
+    }
+
+    #[test]
+    fn test_depth_aggregates_levels() {
+        let mut order_book = OrderBook::new();
+        for (id, bid_or_ask, price, size) in [
+            ("1", BidOrAsk::Bid, dec!(99), dec!(2)),
+            ("2", BidOrAsk::Bid, dec!(99), dec!(3)),
+            ("3", BidOrAsk::Bid, dec!(98), dec!(1)),
+            ("4", BidOrAsk::Bid, dec!(97), dec!(1)),
+            ("5", BidOrAsk::Ask, dec!(101), dec!(4)),
+        ] {
+            order_book.place_limit_order(Order::new(id.to_string(), bid_or_ask, price, size)).unwrap();
+        }
+        order_book
+            .place_limit_order(Order::new("6".to_string(), BidOrAsk::Ask, dec!(102), dec!(10)).with_display_size(dec!(2)))
+            .unwrap();
+
+        let depth = order_book.depth(2);
+
+        let level = |price, size, order_count| PriceLevel { price, size, order_count };
+        assert_eq!(depth.bids, vec![level(dec!(99), dec!(5), 2), level(dec!(98), dec!(1), 1)]);
+        assert_eq!(depth.asks, vec![level(dec!(101), dec!(4), 1), level(dec!(102), dec!(2), 1)], "Expected only the displayed iceberg slice");
+        assert_eq!(order_book.best_bid_price(), Some(dec!(99)));
+        assert_eq!(order_book.best_ask_price(), Some(dec!(101)));
+        assert_eq!(order_book.spread(), Some(dec!(2)));
+        assert_eq!(order_book.mid_price(), Some(dec!(100)));
+    }
+
+    #[test]
+    fn test_depth_of_empty_book() {
+        let mut order_book = OrderBook::new();
+        order_book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(99), dec!(1))).unwrap();
+
+        let depth = order_book.depth(5);
+
+        assert_eq!(depth.bids.len(), 1);
+        assert!(depth.asks.is_empty());
+        assert_eq!(order_book.spread(), None);
+        assert_eq!(order_book.mid_price(), None);
+        assert!(OrderBook::new().depth(0).bids.is_empty());
+    }
+}
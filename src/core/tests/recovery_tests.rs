@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod tests_recovery {
+    use crate::core::engine::{Command, Engine, Event};
+    use crate::core::error::{MatchingError, StorageError};
+    use crate::core::journal::{read_journal, Journal, JournalConfig};
+    use crate::core::order::BidOrAsk;
+    use crate::core::order_book::OrderBook;
+    use crate::core::recovery::recover;
+    use crate::core::snapshot_store::SnapshotStore;
+    use crate::core::tests::support::{new_order, queues, snapshot, TestDirectory};
+    use rust_decimal::Decimal;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Snapshot and journal directories inside a fresh test directory
+    fn test_directories(name: &str) -> (PathBuf, PathBuf, TestDirectory) {
+        let root = TestDirectory::new(&format!("recovery-{}", name));
+        (root.path().join("snapshots"), root.path().join("journal"), root)
+    }
+
+    // A mix of resting, crossing, amending and canceling commands
+    fn commands(from: u64, to: u64) -> Vec<Command> {
+        (from..to)
+            .map(|id| match id % 5 {
+                0 => new_order(&id.to_string(), "alice", BidOrAsk::Ask, Decimal::from(100 + id % 3), Decimal::from(4)),
+                1 => new_order(&id.to_string(), "alice", BidOrAsk::Bid, Decimal::from(99 + id % 3), Decimal::from(3)),
+                2 => Command::Amend { order_id: (id - 2).to_string(), price: Decimal::from(103), size: Decimal::from(2) },
+                3 => Command::Cancel { order_id: (id - 2).to_string() },
+                _ => new_order(&id.to_string(), "alice", BidOrAsk::Bid, Decimal::from(101), Decimal::from(5)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recovers_from_snapshot_and_journal() {
+        let (snapshots, journal, _root) = test_directories("snapshot-and-journal");
+        let store = SnapshotStore::new(&snapshots).unwrap();
+        let mut engine = Engine::new("BTC/USD".to_string())
+            .with_journal(Journal::open(&journal, JournalConfig::default()).unwrap());
+        for command in commands(0, 20) {
+            engine.process(command);
+        }
+        let taken = snapshot(&mut engine);
+        store.save(&taken).unwrap();
+        let replayed = commands(20, 40);
+        let replayed_count = replayed.len();
+        for command in replayed {
+            engine.process(command);
+        }
+        let expected = snapshot(&mut engine);
+        drop(engine); // The crash
+
+        let (mut recovered, report) = recover("BTC/USD", OrderBook::new(), &store, &journal).unwrap();
+        let actual = snapshot(&mut recovered);
+
+        assert_eq!(report.snapshot_log_seq, Some(taken.log_seq()));
+        assert_eq!(report.replayed_commands, replayed_count + 2, "Expected the two snapshot commands to be replayed too");
+        assert_eq!(report.replayed_logs as i64, expected.log_seq() - taken.log_seq());
+        assert_eq!(report.truncated_bytes, 0);
+        assert_eq!(queues(&actual), queues(&expected));
+        assert_eq!((actual.log_seq(), actual.trade_seq()), (expected.log_seq(), expected.trade_seq()));
+    }
+
+    #[test]
+    fn test_recovers_from_journal_alone() {
+        let (snapshots, journal, _root) = test_directories("journal-alone");
+        let mut engine = Engine::new("BTC/USD".to_string())
+            .with_journal(Journal::open(&journal, JournalConfig::default()).unwrap());
+        for command in commands(0, 30) {
+            engine.process(command);
+        }
+        engine.process(Command::Halt);
+        let expected = snapshot(&mut engine);
+        drop(engine);
+
+        let store = SnapshotStore::new(&snapshots).unwrap();
+        let (mut recovered, report) = recover("BTC/USD", OrderBook::new(), &store, &journal).unwrap();
+
+        assert_eq!(report.snapshot_log_seq, None);
+        assert_eq!(snapshot(&mut recovered), expected, "Expected replay from an empty book to rebuild the exact state");
+        assert!(recovered.book().is_halted());
+    }
+
+    #[test]
+    fn test_truncates_torn_tail() {
+        let (snapshots, journal, _root) = test_directories("torn");
+        let mut engine = Engine::new("BTC/USD".to_string())
+            .with_journal(Journal::open(&journal, JournalConfig::default()).unwrap());
+        for command in commands(0, 10) {
+            engine.process(command);
+        }
+        drop(engine);
+        let segment = fs::read_dir(&journal).unwrap().next().unwrap().unwrap().path();
+        let length = fs::metadata(&segment).unwrap().len();
+        fs::OpenOptions::new().write(true).open(&segment).unwrap().set_len(length - 5).unwrap();
+
+        let store = SnapshotStore::new(&snapshots).unwrap();
+        let (recovered, report) = recover("BTC/USD", OrderBook::new(), &store, &journal).unwrap();
+
+        assert!(report.truncated_bytes > 0);
+        assert_eq!(report.replayed_commands, 10, "Expected the command before the torn log to be replayed");
+        assert!(read_journal(&journal).unwrap().torn_tail.is_none());
+        let mut recovered = recovered.with_journal(Journal::open(&journal, JournalConfig::default()).unwrap());
+        recovered.process(Command::Halt);
+        drop(recovered);
+        let (_, report) = recover("BTC/USD", OrderBook::new(), &store, &journal).unwrap();
+        assert_eq!(report.replayed_commands, 11);
+    }
+
+    #[test]
+    fn test_skips_corrupt_snapshot() {
+        let (snapshots, journal, _root) = test_directories("corrupt-snapshot");
+        let store = SnapshotStore::new(&snapshots).unwrap();
+        let mut engine = Engine::new("BTC/USD".to_string())
+            .with_journal(Journal::open(&journal, JournalConfig::default()).unwrap());
+        for command in commands(0, 10) {
+            engine.process(command);
+        }
+        let older = snapshot(&mut engine);
+        store.save(&older).unwrap();
+        for command in commands(10, 20) {
+            engine.process(command);
+        }
+        let newer = store.save(&snapshot(&mut engine)).unwrap();
+        let expected = snapshot(&mut engine);
+        drop(engine);
+        let mut bytes = fs::read(&newer).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        fs::write(&newer, &bytes).unwrap();
+
+        let (mut recovered, report) = recover("BTC/USD", OrderBook::new(), &store, &journal).unwrap();
+
+        assert_eq!(report.skipped_snapshots, 1);
+        assert_eq!(report.snapshot_log_seq, Some(older.log_seq()));
+        assert_eq!(queues(&snapshot(&mut recovered)), queues(&expected));
+    }
+
+    #[test]
+    fn test_detects_sequence_gap() {
+        let (snapshots, journal, _root) = test_directories("gap");
+        let mut writer = Journal::open(&journal, JournalConfig::default()).unwrap();
+        writer.append_command(5, &Command::Halt).unwrap(); // Nothing explains logs 1 to 5
+        drop(writer);
+
+        let store = SnapshotStore::new(&snapshots).unwrap();
+        let result = recover("BTC/USD", OrderBook::new(), &store, &journal);
+
+        assert!(matches!(result, Err(StorageError::SequenceMismatch { expected: 0, actual: 5 })));
+    }
+
+    #[test]
+    fn test_refused_commands_stay_refused() {
+        let (snapshots, journal, _root) = test_directories("refused");
+        let store = SnapshotStore::new(&snapshots).unwrap();
+        let mut engine = Engine::new("BTC/USD".to_string())
+            .with_journal(Journal::open(&journal, JournalConfig::default()).unwrap());
+        engine.process(Command::Halt);
+        let events = engine.process(new_order("1", "alice", BidOrAsk::Bid, Decimal::from(100), Decimal::from(1)));
+        assert!(matches!(events[..], [Event::Log(_), Event::Error(MatchingError::BookHalted)]));
+        engine.process(Command::Resume);
+        store.save(&snapshot(&mut engine)).unwrap();
+        drop(engine);
+
+        let (recovered, _) = recover("BTC/USD", OrderBook::new(), &store, &journal).unwrap();
+
+        assert!(recovered.book().get_order("1").is_none(), "Expected the refused order not to be replayed");
+        assert!(!recovered.book().is_halted());
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests_snapshot_store {
+    use crate::core::engine::Engine;
+    use crate::core::error::StorageError;
+    use crate::core::order::{BidOrAsk, Order, OrderType, TimeInForce};
+    use crate::core::order_book::OrderBook;
+    use crate::core::snapshot::SnapshotData;
+    use crate::core::snapshot_store::{read_snapshot, SnapshotStore};
+    use crate::core::tests::support::TestDirectory;
+    use rust_decimal_macros::dec;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    fn create_snapshot(pair: &str) -> SnapshotData {
+        let mut book = OrderBook::new();
+        book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(101), dec!(5)).with_owner("alice".to_string()))
+            .unwrap();
+        book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(101), dec!(12)).with_display_size(dec!(4)))
+            .unwrap();
+        book.place_limit_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(101), dec!(2))).unwrap();
+        book.place_limit_order(
+            Order::new("4".to_string(), BidOrAsk::Bid, dec!(99.5), dec!(3))
+                .with_time_in_force(TimeInForce::GoodTillDate(SystemTime::UNIX_EPOCH + Duration::from_secs(90))),
+        )
+        .unwrap();
+        book.place_stop_order(
+            Order::new("5".to_string(), BidOrAsk::Ask, dec!(98), dec!(1)).with_order_type(OrderType::StopLimit(dec!(99))),
+        )
+        .unwrap();
+        book.snapshot(pair.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let snapshot = create_snapshot("BTC/USD");
+
+        let decoded = SnapshotData::decode(&snapshot.encode()).unwrap();
+
+        assert_eq!(decoded, snapshot);
+        assert_eq!(decoded.stop_orders.len(), 1);
+        assert_eq!(decoded.last_trade_price(), Some(dec!(101)));
+    }
+
+    #[test]
+    fn test_save_and_load_latest() {
+        let directory = TestDirectory::new("snapshot-store-save-load");
+        let store = SnapshotStore::new(directory.path()).unwrap();
+        let older = Engine::new("BTC/USD".to_string()).book().snapshot("BTC/USD".to_string()).unwrap();
+        let latest = create_snapshot("BTC/USD");
+        store.save(&latest).unwrap();
+        store.save(&older).unwrap();
+        store.save(&create_snapshot("ETH/USD")).unwrap();
+
+        let loaded = store.load_latest("BTC/USD").unwrap().expect("Expected a snapshot");
+        let engine = Engine::from_snapshot(OrderBook::new(), loaded.clone()).unwrap();
+
+        assert_eq!(loaded, latest);
+        assert_eq!(engine.pair(), "BTC/USD");
+        assert!(engine.book().get_order("2").is_some());
+        assert!(store.load_latest("SOL/USD").unwrap().is_none());
+        let leftovers = fs::read_dir(directory.path())
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension().is_some_and(|extension| extension == "tmp"))
+            .count();
+        assert_eq!(leftovers, 0, "Expected no temporary files to be left behind");
+    }
+
+    #[test]
+    fn test_similar_pairs_do_not_share_files() {
+        let directory = TestDirectory::new("snapshot-store-similar-pairs");
+        let store = SnapshotStore::new(directory.path()).unwrap();
+        let slash = Engine::new("BTC/USD".to_string()).book().snapshot("BTC/USD".to_string()).unwrap();
+        let underscore = create_snapshot("BTC_USD");
+        store.save(&slash).unwrap();
+        store.save(&underscore).unwrap(); // Newer, and "/" used to map to "_".
+
+        assert_eq!(store.load_latest("BTC/USD").unwrap(), Some(slash.clone()));
+        let (loaded, skipped) = store.load_newest_valid("BTC/USD").unwrap();
+        assert_eq!((loaded, skipped), (Some(slash), 0));
+        assert_eq!(store.load_latest("BTC_USD").unwrap(), Some(underscore));
+    }
+
+    #[test]
+    fn test_refuses_corrupt_snapshot() {
+        let directory = TestDirectory::new("snapshot-store-corrupt");
+        let store = SnapshotStore::new(directory.path()).unwrap();
+        let path = store.save(&create_snapshot("BTC/USD")).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        fs::write(&path, &bytes).unwrap();
+
+        let result = store.load_latest("BTC/USD");
+
+        assert!(matches!(result, Err(StorageError::ChecksumMismatch { .. })));
+        fs::write(&path, &bytes[..10]).unwrap();
+        assert!(matches!(read_snapshot(&path), Err(StorageError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_refuses_incompatible_snapshot() {
+        let mut bytes = create_snapshot("BTC/USD").encode();
+        bytes[4] = 3; // A format version from the future
+
+        assert!(matches!(SnapshotData::decode(&bytes), Err(StorageError::UnsupportedVersion(3))));
+        assert!(matches!(SnapshotData::decode(b"not a snapshot"), Err(StorageError::BadMagic)));
+        assert!(matches!(SnapshotData::decode(b"ME"), Err(StorageError::BadMagic)));
+    }
+}
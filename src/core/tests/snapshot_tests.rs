@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests_snapshot {
+    use crate::core::error::MatchingError;
+    use crate::core::limit::Limit;
+    use crate::core::order::{BidOrAsk, Order};
+    use crate::core::snapshot::Snapshot;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_construct_snapshot() {
+        let mut ask = Limit::new(dec!(101));
+        ask.add_order(Order::new("1".to_string(), BidOrAsk::Ask, dec!(101), dec!(5)), 1);
+        ask.add_order(Order::new("2".to_string(), BidOrAsk::Ask, dec!(101), dec!(3)), 2);
+        let mut bid = Limit::new(dec!(99));
+        bid.add_order(Order::new("3".to_string(), BidOrAsk::Bid, dec!(99), dec!(4)), 3);
+
+        let snapshot_data = Snapshot::new("BTC/USD".to_string())
+            .construct_snapshot(vec![ask], vec![bid], vec![], None, false, 0, 3)
+            .unwrap();
+
+        let ids = snapshot_data.orders.iter().map(|order| order.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_construct_snapshot_side_mismatch() {
+        let mut ask = Limit::new(dec!(101));
+        ask.add_order(Order::new("1".to_string(), BidOrAsk::Bid, dec!(101), dec!(5)), 1);
+
+        let result = Snapshot::new("BTC/USD".to_string()).construct_snapshot(vec![ask], vec![], vec![], None, false, 0, 1);
+
+        assert_eq!(result.err(), Some(MatchingError::SideMismatch("1".to_string())));
+    }
+}
+
+#[cfg(test)]
+mod tests_snapshot_round_trip {
+    use crate::core::engine::{Engine, Event};
+    use crate::core::order::{BidOrAsk, Order};
+    use crate::core::order_book::OrderBook;
+    use crate::core::tests::support::{queues, random_commands, snapshot};
+    use rust_decimal::Decimal;
+
+    fn sequences(events: Vec<Event>) -> Vec<Option<i64>> {
+        events
+            .iter()
+            .map(|event| match event {
+                Event::Log(log) => Some(log.get_seq()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshot_restore_round_trip() {
+        for seed in 0..50 {
+            let commands = random_commands(seed, 200);
+            let (before, after) = commands.split_at(100);
+
+            let mut original = Engine::new("BTC/USD".to_string());
+            for command in before {
+                original.process(command.clone());
+            }
+            let taken = snapshot(&mut original);
+            let mut restored = Engine::from_snapshot(OrderBook::new(), taken.clone()).unwrap();
+            assert_eq!(snapshot(&mut restored), taken, "Restoring changed the snapshot for seed {}", seed);
+
+            for command in after {
+                let expected = sequences(original.process(command.clone()));
+                assert_eq!(sequences(restored.process(command.clone())), expected, "Diverged on seed {}", seed);
+            }
+            let (continued, expected) = (snapshot(&mut restored), snapshot(&mut original));
+            assert_eq!(queues(&continued), queues(&expected), "Diverged on seed {}", seed);
+            assert_eq!((continued.log_seq(), continued.trade_seq()), (expected.log_seq(), expected.trade_seq()));
+        }
+    }
+
+    #[test]
+    fn test_restore_keeps_queue_order() {
+        let mut book = OrderBook::new();
+        book.place_limit_order(Order::new("1".to_string(), BidOrAsk::Ask, Decimal::from(100), Decimal::from(5))).unwrap();
+        book.place_limit_order(Order::new("2".to_string(), BidOrAsk::Ask, Decimal::from(100), Decimal::from(5))).unwrap();
+        book.amend_order("1", Decimal::from(100), Decimal::from(6)).unwrap(); // Order 1 loses priority
+
+        let mut restored = OrderBook::new();
+        restored.restore(book.snapshot("BTC/USD".to_string()).unwrap()).unwrap();
+        let ids: Vec<&str> = restored.ask_limits().flat_map(|limit| &limit.orders).map(|order| order.id.as_str()).collect();
+
+        assert_eq!(ids, vec!["2", "1"]);
+        assert!(restored.restore(book.snapshot("BTC/USD".to_string()).unwrap()).is_err(), "Expected duplicate IDs to be refused");
+    }
+}
@@ -0,0 +1,111 @@
+#![cfg(test)]
+use crate::core::engine::{Command, Engine, Event};
+use crate::core::order::{BidOrAsk, Order, OrderType, SelfTradePrevention, TimeInForce};
+use crate::core::order_book::OrderBook;
+use crate::core::snapshot::SnapshotData;
+use rust_decimal::Decimal;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// Small xorshift generator, so every seed replays the same command stream
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+/// Creates an engine whose book resolves self trades with a mode picked by the seed.
+pub(crate) fn engine(seed: u64) -> Engine {
+    let mut book = OrderBook::new();
+    book.set_self_trade_prevention(match seed % 5 {
+        0 => None,
+        1 => Some(SelfTradePrevention::CancelNewest),
+        2 => Some(SelfTradePrevention::CancelOldest),
+        3 => Some(SelfTradePrevention::CancelBoth),
+        _ => Some(SelfTradePrevention::DecrementAndCancel),
+    });
+    Engine::with_book("BTC/USD".to_string(), book)
+}
+
+/// Creates a `Command::NewOrder` for a limit order.
+pub(crate) fn new_order(id: &str, owner: &str, bid_or_ask: BidOrAsk, price: Decimal, size: Decimal) -> Command {
+    Command::NewOrder(Order::new(id.to_string(), bid_or_ask, price, size).with_owner(owner.to_string()))
+}
+
+/// Generates a command stream that is the same for every run with the same seed.
+///
+/// Orders get IDs `0..count` and one of three owners; cancels and amendments target a random
+/// earlier ID, which may no longer be resting.
+pub(crate) fn random_commands(seed: u64, count: usize) -> Vec<Command> {
+    let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+    let epoch = SystemTime::UNIX_EPOCH;
+    (0..count)
+        .map(|id| {
+            let bid_or_ask = if rng.next(2) == 0 { BidOrAsk::Bid } else { BidOrAsk::Ask };
+            let price = Decimal::from(95 + rng.next(11));
+            let size = Decimal::from(1 + rng.next(10));
+            let order = Order::new(id.to_string(), bid_or_ask, price, size).with_owner(rng.next(3).to_string());
+            let target = rng.next(id as u64 + 1).to_string();
+            match rng.next(10) {
+                0 => Command::NewOrder(order.with_display_size(Decimal::from(1 + rng.next(3)))),
+                1 => Command::NewOrder(order.with_order_type(OrderType::Market)),
+                2 => Command::NewOrder(order.with_order_type(OrderType::StopLimit(Decimal::from(95 + rng.next(11))))),
+                3 => Command::NewOrder(order.with_time_in_force(TimeInForce::GoodTillDate(
+                    epoch + Duration::from_secs(rng.next(100)),
+                ))),
+                4 => Command::Cancel { order_id: target },
+                5 => Command::Amend { order_id: target, price, size },
+                6 => Command::Expire { now: epoch + Duration::from_secs(rng.next(100)) },
+                _ => Command::NewOrder(order),
+            }
+        })
+        .collect()
+}
+
+/// Takes a snapshot of the engine's book through `Command::Snapshot`.
+pub(crate) fn snapshot(engine: &mut Engine) -> SnapshotData {
+    match engine.process(Command::Snapshot).pop() {
+        Some(Event::Snapshot(snapshot)) => snapshot,
+        other => panic!("Expected a snapshot, got {:?}", other),
+    }
+}
+
+/// Returns the resting and stop orders of a snapshot in queue order, without the position
+/// tickets each limit hands out, so books rebuilt in different ways can be compared.
+pub(crate) fn queues(snapshot: &SnapshotData) -> Vec<(String, Decimal, Decimal, Decimal)> {
+    snapshot
+        .orders
+        .iter()
+        .chain(&snapshot.stop_orders)
+        .map(|order| (order.id.clone(), order.price, order.size, order.hidden_size))
+        .collect()
+}
+
+/// A fresh temporary directory per test, so tests running in parallel do not see each other's
+/// files. The directory is removed when the value is dropped, whether the test passes or not.
+pub(crate) struct TestDirectory(PathBuf);
+
+impl TestDirectory {
+    /// Reserves the directory; it is created by whatever first writes to it.
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("matching-engine-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        TestDirectory(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests_trigger_book {
+    use crate::core::order::{BidOrAsk, Order, OrderType};
+    use crate::core::trigger_book::TriggerBook;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    // Helper function to create a stop order
+    fn create_stop(id: &str, bid_or_ask: BidOrAsk, stop_price: Decimal) -> Order {
+        Order::new(id.to_string(), bid_or_ask, stop_price, dec!(1)).with_order_type(OrderType::StopMarket(stop_price))
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut triggers = TriggerBook::new();
+        triggers.add(create_stop("1", BidOrAsk::Bid, dec!(105)));
+        triggers.add(Order::new("2".to_string(), BidOrAsk::Bid, dec!(105), dec!(1)));
+
+        assert_eq!(triggers.len(), 1, "Expected orders without a stop price to be ignored");
+        assert!(triggers.get("1").is_some());
+        assert!(triggers.remove("1").is_some());
+        assert!(triggers.remove("1").is_none());
+        assert!(triggers.is_empty());
+    }
+
+    #[test]
+    fn test_take_triggered_in_activation_order() {
+        let mut triggers = TriggerBook::new();
+        triggers.add(create_stop("b1", BidOrAsk::Bid, dec!(103)));
+        triggers.add(create_stop("b2", BidOrAsk::Bid, dec!(101)));
+        triggers.add(create_stop("b3", BidOrAsk::Bid, dec!(101)));
+        triggers.add(create_stop("b4", BidOrAsk::Bid, dec!(110)));
+        triggers.add(create_stop("s1", BidOrAsk::Ask, dec!(95)));
+
+        let ids = triggers
+            .take_triggered(dec!(103))
+            .into_iter()
+            .map(|order| order.id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, vec!["b2", "b3", "b1"], "Expected lowest buy stops first, then arrival order");
+        assert_eq!(triggers.len(), 2);
+    }
+
+    #[test]
+    fn test_take_triggered_sell_stops() {
+        let mut triggers = TriggerBook::new();
+        triggers.add(create_stop("s1", BidOrAsk::Ask, dec!(95)));
+        triggers.add(create_stop("s2", BidOrAsk::Ask, dec!(98)));
+        triggers.add(create_stop("s3", BidOrAsk::Ask, dec!(90)));
+
+        let ids = triggers
+            .take_triggered(dec!(95))
+            .into_iter()
+            .map(|order| order.id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, vec!["s2", "s1"], "Expected highest sell stops first");
+        assert!(triggers.get("s3").is_some());
+    }
+}
@@ -0,0 +1,110 @@
+#![allow(dead_code)]
+use crate::core::order::{BidOrAsk, Order};
+use rust_decimal::Decimal;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+/// Holds stop orders until the last trade price reaches their stop price.
+///
+/// Buy stops trigger when the last trade price rises to or above their stop price, and sell stops
+/// trigger when it falls to or below it. Stops are released in the order the price would reach
+/// them (lowest buy stop first, highest sell stop first), and in arrival order for equal stop
+/// prices, so activation is deterministic.
+#[derive(Debug, Default)]
+pub struct TriggerBook {
+    buy_stops: BTreeMap<(Decimal, u64), Order>,           // Buy stops, lowest stop price first.
+    sell_stops: BTreeMap<(Reverse<Decimal>, u64), Order>, // Sell stops, highest stop price first.
+    index: HashMap<String, (BidOrAsk, Decimal, u64)>,     // Side, stop price and arrival of each stop.
+    next_position: u64,                                   // The arrival ticket handed to the next stop.
+}
+
+impl TriggerBook {
+    /// Creates a new, empty trigger book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if no stop orders are pending.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns the number of pending stop orders.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Adds a stop order to the trigger book.
+    ///
+    /// # Arguments
+    /// * `order` - The stop `Order`. Orders without a stop price are ignored.
+    pub(crate) fn add(&mut self, order: Order) {
+        let Some(stop_price) = order.stop_price() else {
+            return;
+        };
+        let position = self.next_position;
+        self.next_position += 1;
+
+        self.index
+            .insert(order.id.clone(), (order.bid_or_ask.clone(), stop_price, position));
+        match order.bid_or_ask {
+            BidOrAsk::Bid => self.buy_stops.insert((stop_price, position), order),
+            BidOrAsk::Ask => self.sell_stops.insert((Reverse(stop_price), position), order),
+        };
+    }
+
+    /// Looks up a pending stop order by its ID.
+    pub(crate) fn get(&self, id: &str) -> Option<&Order> {
+        let (bid_or_ask, stop_price, position) = self.index.get(id)?;
+        match bid_or_ask {
+            BidOrAsk::Bid => self.buy_stops.get(&(*stop_price, *position)),
+            BidOrAsk::Ask => self.sell_stops.get(&(Reverse(*stop_price), *position)),
+        }
+    }
+
+    /// Returns every pending stop order, buy stops first, each side in activation order.
+    pub(crate) fn orders(&self) -> impl Iterator<Item = &Order> {
+        self.buy_stops.values().chain(self.sell_stops.values())
+    }
+
+    /// Removes a pending stop order by its ID.
+    ///
+    /// # Returns
+    /// * The removed order, or `None` if no pending stop has the given ID.
+    pub(crate) fn remove(&mut self, id: &str) -> Option<Order> {
+        let (bid_or_ask, stop_price, position) = self.index.remove(id)?;
+        match bid_or_ask {
+            BidOrAsk::Bid => self.buy_stops.remove(&(stop_price, position)),
+            BidOrAsk::Ask => self.sell_stops.remove(&(Reverse(stop_price), position)),
+        }
+    }
+
+    /// Removes and returns every stop order triggered by the given last trade price.
+    ///
+    /// # Arguments
+    /// * `last_price` - The price of the most recent trade.
+    ///
+    /// # Returns
+    /// * The triggered orders, buy stops first, each side in activation order.
+    pub(crate) fn take_triggered(&mut self, last_price: Decimal) -> Vec<Order> {
+        let mut triggered = Vec::new();
+
+        while let Some(entry) = self.buy_stops.first_entry() {
+            if entry.key().0 > last_price {
+                break;
+            }
+            triggered.push(entry.remove());
+        }
+        while let Some(entry) = self.sell_stops.first_entry() {
+            if entry.key().0 .0 < last_price {
+                break;
+            }
+            triggered.push(entry.remove());
+        }
+
+        for order in &triggered {
+            self.index.remove(&order.id);
+        }
+        triggered
+    }
+}